@@ -0,0 +1,352 @@
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    asset::{Assets, LoadedFolder},
+    color::Alpha,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        observer::Trigger,
+        query::{Has, With},
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Query, Res},
+    },
+    log::error,
+    math::Vec3Swizzles,
+    sprite::Sprite,
+    time::Time,
+    transform::components::Transform,
+};
+
+use crate::{
+    DuringWave,
+    archetype::{EnemyArchetypeFolder, EnemyDefinition},
+    enemy::{
+        Enemy, EnemyCurrentTarget, EnemyKilled, OnSecondPath, PathProgress, SpawnLocation,
+        spawn_archetype,
+    },
+    grid::{GridIndex, HexGridRenderRadius, HexSpatialGrid},
+    stats::{Health, MaxHealth, Wave},
+    tower::{Tower, TowerIndex},
+    wave::EnemyModifiers,
+};
+
+pub struct AbilityPlugin;
+
+impl Plugin for AbilityPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_observer(split_on_death);
+        app.add_systems(
+            FixedUpdate,
+            (heal_allies, reveal_stealthed).in_set(DuringWave),
+        );
+    }
+}
+
+/// Distance along the path between the children of a split enemy.
+static SPLIT_SPREAD: f32 = 6.0;
+/// Opacity of stealthed enemies that are not revealed.
+static STEALTH_ALPHA: f32 = 0.3;
+
+/// Spawns `count` enemies of the named archetype when killed.
+#[derive(Component, Clone, Debug)]
+pub struct Splitter {
+    pub enemy: String,
+    pub count: u32,
+}
+
+/// Heals other enemies within `radius` hexes by `per_second`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Healer {
+    pub radius: i32,
+    pub per_second: f32,
+}
+
+/// Cannot be targeted unless `Revealed`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Stealth;
+
+/// Stealthed enemy that is currently in range of a `Detector`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Revealed;
+
+/// Reveals stealthed enemies within `radius` hexes of the tower.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Detector {
+    pub radius: i32,
+}
+
+/// Whether towers are allowed to shoot at an enemy.
+pub fn is_targetable(stealth: bool, revealed: bool) -> bool {
+    !stealth || revealed
+}
+
+pub fn split_on_death(
+    trigger: Trigger<EnemyKilled>,
+    mut commands: Commands,
+    splitters: Query<(
+        &Splitter,
+        &Transform,
+        &EnemyCurrentTarget,
+        &PathProgress,
+        Has<OnSecondPath>,
+    )>,
+    wave: Res<Wave>,
+    archetypes: Res<EnemyArchetypeFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<EnemyDefinition>>,
+) {
+    let Ok((splitter, transform, target, progress, second_path)) = splitters.get(trigger.target())
+    else {
+        return;
+    };
+    let Some(handle) = archetypes.find(&splitter.enemy, &folders, &definitions) else {
+        error!("unknown enemy archetype {}", splitter.enemy);
+        return;
+    };
+    let definition = definitions.get(&handle).unwrap();
+    for i in 0..splitter.count {
+        spawn_archetype(
+            &mut commands,
+            handle.clone(),
+            definition,
+            wave.0,
+            &EnemyModifiers::default(),
+            SpawnLocation {
+                position: transform.translation.xy(),
+                target: target.0,
+                progress: (progress.0 - i as f32 * SPLIT_SPREAD).max(0.0),
+                second_path,
+            },
+        );
+    }
+}
+
+pub fn heal_allies(
+    healers: Query<(Entity, &Healer, &Transform), With<Enemy>>,
+    mut enemies: Query<(&mut Health, &MaxHealth), With<Enemy>>,
+    spatial_grid: Res<HexSpatialGrid>,
+    size: Res<HexGridRenderRadius>,
+    time: Res<Time>,
+) {
+    for (healer, ability, transform) in healers {
+        let index = GridIndex::from_world_pos(transform.translation.xy(), **size);
+        for ally in spatial_grid.get_within(&index, ability.radius) {
+            if ally == healer {
+                continue;
+            }
+            let Ok((mut h, max)) = enemies.get_mut(ally) else {
+                continue;
+            };
+            if h.0 > 0.0 && h.0 < max.0 {
+                h.0 = (h.0 + ability.per_second * time.delta_secs()).min(max.0);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn reveal_stealthed(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Transform, Has<Revealed>, Option<&mut Sprite>), With<Stealth>>,
+    detectors: Query<(&TowerIndex, &Detector), With<Tower>>,
+    size: Res<HexGridRenderRadius>,
+) {
+    for (enemy, transform, was_revealed, sprite) in enemies {
+        let index = GridIndex::from_world_pos(transform.translation.xy(), **size);
+        let revealed = detectors
+            .iter()
+            .any(|(tower, detector)| tower.distance(&index) <= detector.radius);
+        if revealed == was_revealed {
+            continue;
+        }
+        if revealed {
+            commands.entity(enemy).insert(Revealed);
+        } else {
+            commands.entity(enemy).remove::<Revealed>();
+        }
+        if let Some(mut sprite) = sprite {
+            sprite
+                .color
+                .set_alpha(if revealed { 1.0 } else { STEALTH_ALPHA });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        MinimalPlugins,
+        app::{App, Update},
+        asset::Handle,
+        color::Color,
+    };
+
+    use super::*;
+    use crate::{
+        archetype::WaveScaling,
+        enemy::{EnemyLayer, Shield},
+    };
+
+    const TILE: f32 = 50.0;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(HexGridRenderRadius(TILE));
+        app.insert_resource(HexSpatialGrid::default());
+        app.insert_resource(Wave(0));
+        app
+    }
+
+    fn definition(name: &str) -> EnemyDefinition {
+        EnemyDefinition {
+            name: name.to_string(),
+            sprite: Handle::default(),
+            tint: Color::WHITE,
+            health: 10.0,
+            speed: 50.0,
+            armor: 0.0,
+            gold: 1,
+            leak_damage: 1.0,
+            size: 8.0,
+            weight: 1.0,
+            min_wave: 0,
+            abilities: vec![],
+            scaling: WaveScaling::default(),
+            layer: EnemyLayer::Ground,
+            boss: false,
+            phases: vec![],
+        }
+    }
+
+    #[test]
+    fn splitter_spawns_children_towards_its_target() {
+        let mut app = app();
+        let mut definitions = Assets::<EnemyDefinition>::default();
+        let child = definitions.add(definition("Spawnling"));
+        let mut folders = Assets::<LoadedFolder>::default();
+        let folder = folders.add(LoadedFolder {
+            handles: vec![child.untyped()],
+        });
+        app.insert_resource(definitions);
+        app.insert_resource(folders);
+        app.insert_resource(EnemyArchetypeFolder(folder));
+        app.add_observer(split_on_death);
+
+        let target = GridIndex::new(3, 1);
+        let parent = app
+            .world_mut()
+            .spawn((
+                Enemy,
+                Splitter {
+                    enemy: "Spawnling".to_string(),
+                    count: 3,
+                },
+                Transform::from_xyz(100.0, 0.0, 0.0),
+                EnemyCurrentTarget(target),
+                PathProgress(100.0),
+            ))
+            .id();
+        app.world_mut().trigger_targets(EnemyKilled, parent);
+        app.world_mut().despawn(parent);
+        app.world_mut().flush();
+
+        let mut children = app
+            .world_mut()
+            .query::<(&EnemyCurrentTarget, &PathProgress)>();
+        let children: Vec<_> = children.iter(app.world()).collect();
+        assert_eq!(children.len(), 3);
+        for (t, progress) in children {
+            assert_eq!(t.0, target);
+            assert!(progress.0 <= 100.0 && progress.0 >= 100.0 - 2.0 * SPLIT_SPREAD);
+        }
+    }
+
+    #[test]
+    fn healer_heals_nearby_allies_only() {
+        let mut app = app();
+        let healer = app
+            .world_mut()
+            .spawn((
+                Enemy,
+                Healer {
+                    radius: 1,
+                    per_second: 8.0,
+                },
+                Health(5.0),
+                MaxHealth(10.0),
+                Transform::default(),
+            ))
+            .id();
+        let near = app
+            .world_mut()
+            .spawn((Enemy, Health(4.0), MaxHealth(6.0)))
+            .id();
+        let far = app
+            .world_mut()
+            .spawn((Enemy, Health(4.0), MaxHealth(10.0)))
+            .id();
+        let mut grid = app.world_mut().resource_mut::<HexSpatialGrid>();
+        grid.update(GridIndex::new(0, 0), healer);
+        grid.update(GridIndex::new(1, 0), near);
+        grid.update(GridIndex::new(3, 0), far);
+
+        let heal_for = |app: &mut App, millis: u64| {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(millis));
+            app.world_mut().run_system_cached(heal_allies).unwrap();
+        };
+        let health = |app: &App, e: Entity| app.world().get::<Health>(e).unwrap().0;
+
+        // 8 per second for an eighth of a second.
+        heal_for(&mut app, 125);
+        assert_eq!(health(&app, near), 5.0);
+        assert_eq!(health(&app, healer), 5.0);
+        assert_eq!(health(&app, far), 4.0);
+
+        // Another 2 would go past the maximum of 6.
+        heal_for(&mut app, 250);
+        assert_eq!(health(&app, near), 6.0);
+    }
+
+    #[test]
+    fn shield_absorbs_damage_first() {
+        let mut shield = Shield(10.0);
+        assert_eq!(shield.absorb(4.0), 0.0);
+        assert_eq!(shield.absorb(10.0), 4.0);
+        assert_eq!(shield.0, 0.0);
+    }
+
+    #[test]
+    fn detector_reveals_stealthed_enemies_in_range() {
+        let mut app = app();
+        app.add_systems(Update, reveal_stealthed);
+
+        let near = GridIndex::new(1, 0).to_world_pos(TILE);
+        let far = GridIndex::new(4, 0).to_world_pos(TILE);
+        let visible = app
+            .world_mut()
+            .spawn((Enemy, Stealth, Transform::from_xyz(near.x, near.y, 0.0)))
+            .id();
+        let hidden = app
+            .world_mut()
+            .spawn((Enemy, Stealth, Transform::from_xyz(far.x, far.y, 0.0)))
+            .id();
+        app.world_mut().spawn((
+            Tower,
+            TowerIndex(GridIndex::new(0, 0)),
+            Detector { radius: 2 },
+        ));
+
+        app.update();
+
+        assert!(app.world().get::<Revealed>(visible).is_some());
+        assert!(app.world().get::<Revealed>(hidden).is_none());
+        assert!(is_targetable(false, false));
+        assert!(!is_targetable(true, false));
+        assert!(is_targetable(true, true));
+    }
+}
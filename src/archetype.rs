@@ -0,0 +1,315 @@
+use bevy::{
+    app::Plugin,
+    asset::{Asset, AssetApp, AssetLoader, Assets, Handle, LoadContext, LoadedFolder, io::Reader},
+    color::Color,
+    ecs::{resource::Resource, system::EntityCommands},
+    image::Image,
+    prelude::Deref,
+    reflect::TypePath,
+};
+use rand::{Rng, seq::IndexedRandom};
+use serde::Deserialize;
+
+use crate::{
+    ability::{Healer, Splitter, Stealth},
+    assets::{ENEMY_PLAYER_DAMAGE, ENEMY_RADIUS, RonAssetLoaderError, read_ron},
+    boss::BossPhase,
+    enemy::{EnemyLayer, Regeneration, Shield},
+    wave::EnemyModifiers,
+};
+
+pub struct ArchetypePlugin;
+
+impl Plugin for ArchetypePlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_asset::<EnemyDefinition>();
+        app.init_asset_loader::<EnemyDefinitionLoader>();
+    }
+}
+
+/// `base` grows by `add_per_wave` every wave and is then multiplied by
+/// `(1 + mul_per_wave)` for every wave.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct ScalingFormula {
+    pub add_per_wave: f32,
+    pub mul_per_wave: f32,
+}
+
+impl ScalingFormula {
+    pub fn apply(&self, base: f32, wave: u32) -> f32 {
+        (base + self.add_per_wave * wave as f32) * (1.0 + self.mul_per_wave).powi(wave as i32)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct WaveScaling {
+    pub health: ScalingFormula,
+    pub speed: ScalingFormula,
+    pub gold: ScalingFormula,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum EnemyAbility {
+    Regeneration { per_second: f32 },
+    Splitter { enemy: String, count: u32 },
+    Healer { radius: i32, per_second: f32 },
+    Shield { amount: f32 },
+    Stealth,
+}
+
+impl EnemyAbility {
+    pub fn insert_into(&self, entity: &mut EntityCommands) {
+        match self {
+            EnemyAbility::Regeneration { per_second } => {
+                entity.insert(Regeneration(*per_second));
+            }
+            EnemyAbility::Splitter { enemy, count } => {
+                entity.insert(Splitter {
+                    enemy: enemy.clone(),
+                    count: *count,
+                });
+            }
+            EnemyAbility::Healer { radius, per_second } => {
+                entity.insert(Healer {
+                    radius: *radius,
+                    per_second: *per_second,
+                });
+            }
+            EnemyAbility::Shield { amount } => {
+                entity.insert(Shield(*amount));
+            }
+            EnemyAbility::Stealth => {
+                entity.insert(Stealth);
+            }
+        }
+    }
+}
+
+/// On-disk representation of an `EnemyDefinition`, see `assets/archetypes`.
+#[derive(Deserialize, Debug)]
+struct EnemyDefinitionFile {
+    name: String,
+    sprite: String,
+    /// Hue, saturation and lightness multiplied onto the sprite.
+    #[serde(default = "default_tint")]
+    tint: (f32, f32, f32),
+    health: f32,
+    speed: f32,
+    #[serde(default)]
+    armor: f32,
+    gold: u32,
+    #[serde(default = "default_leak_damage")]
+    leak_damage: f32,
+    #[serde(default = "default_size")]
+    size: f32,
+    #[serde(default = "default_weight")]
+    weight: f32,
+    #[serde(default)]
+    min_wave: u32,
+    #[serde(default)]
+    abilities: Vec<EnemyAbility>,
+    #[serde(default)]
+    scaling: WaveScaling,
+    #[serde(default)]
+    layer: EnemyLayer,
+    #[serde(default)]
+    boss: bool,
+    #[serde(default)]
+    phases: Vec<BossPhase>,
+}
+
+fn default_tint() -> (f32, f32, f32) {
+    (0.0, 0.0, 1.0)
+}
+fn default_leak_damage() -> f32 {
+    ENEMY_PLAYER_DAMAGE
+}
+fn default_size() -> f32 {
+    ENEMY_RADIUS
+}
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct EnemyDefinition {
+    pub name: String,
+    #[dependency]
+    pub sprite: Handle<Image>,
+    pub tint: Color,
+    pub health: f32,
+    pub speed: f32,
+    pub armor: f32,
+    pub gold: u32,
+    pub leak_damage: f32,
+    pub size: f32,
+    /// Relative chance to be picked among the archetypes unlocked for a wave.
+    pub weight: f32,
+    /// First wave this archetype can appear in.
+    pub min_wave: u32,
+    pub abilities: Vec<EnemyAbility>,
+    pub scaling: WaveScaling,
+    pub layer: EnemyLayer,
+    /// Bosses are only spawned by wave scripts, never picked at random.
+    pub boss: bool,
+    pub phases: Vec<BossPhase>,
+}
+
+/// Stats of an archetype after applying the wave scaling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaledEnemyStats {
+    pub health: f32,
+    pub speed: f32,
+    pub gold: u32,
+}
+
+impl ScaledEnemyStats {
+    pub fn with_modifiers(self, modifiers: &EnemyModifiers) -> Self {
+        Self {
+            health: self.health * modifiers.health,
+            speed: self.speed * modifiers.speed,
+            gold: (self.gold as f32 * modifiers.gold).round() as u32,
+        }
+    }
+}
+
+impl EnemyDefinition {
+    pub fn stats_for_wave(&self, wave: u32) -> ScaledEnemyStats {
+        ScaledEnemyStats {
+            health: self.scaling.health.apply(self.health, wave),
+            speed: self.scaling.speed.apply(self.speed, wave),
+            gold: self.scaling.gold.apply(self.gold as f32, wave).round() as u32,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyDefinitionLoader;
+
+impl AssetLoader for EnemyDefinitionLoader {
+    type Asset = EnemyDefinition;
+    type Settings = ();
+    type Error = RonAssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let file: EnemyDefinitionFile = read_ron(reader).await?;
+        let (h, s, l) = file.tint;
+        Ok(EnemyDefinition {
+            name: file.name,
+            sprite: load_context.load(file.sprite),
+            tint: Color::hsl(h, s, l),
+            health: file.health,
+            speed: file.speed,
+            armor: file.armor,
+            gold: file.gold,
+            leak_damage: file.leak_damage,
+            size: file.size,
+            weight: file.weight,
+            min_wave: file.min_wave,
+            abilities: file.abilities,
+            scaling: file.scaling,
+            layer: file.layer,
+            boss: file.boss,
+            phases: file.phases,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemy.ron"]
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct EnemyArchetypeFolder(pub Handle<LoadedFolder>);
+
+impl EnemyArchetypeFolder {
+    pub fn definitions<'a>(
+        &self,
+        folders: &'a Assets<LoadedFolder>,
+        definitions: &'a Assets<EnemyDefinition>,
+    ) -> impl Iterator<Item = (Handle<EnemyDefinition>, &'a EnemyDefinition)> {
+        folders
+            .get(&self.0)
+            .into_iter()
+            .flat_map(|f| f.handles.iter())
+            .filter_map(|h| h.clone().try_typed::<EnemyDefinition>().ok())
+            .filter_map(|h| definitions.get(&h).map(|d| (h, d)))
+    }
+
+    pub fn find(
+        &self,
+        name: &str,
+        folders: &Assets<LoadedFolder>,
+        definitions: &Assets<EnemyDefinition>,
+    ) -> Option<Handle<EnemyDefinition>> {
+        self.definitions(folders, definitions)
+            .find(|(_, d)| d.name == name)
+            .map(|(h, _)| h)
+    }
+
+    /// Picks one of the archetypes unlocked for `wave`, weighted by `EnemyDefinition::weight`.
+    pub fn pick<R: Rng>(
+        &self,
+        rng: &mut R,
+        wave: u32,
+        folders: &Assets<LoadedFolder>,
+        definitions: &Assets<EnemyDefinition>,
+    ) -> Option<Handle<EnemyDefinition>> {
+        let unlocked: Vec<(Handle<EnemyDefinition>, f32)> = self
+            .definitions(folders, definitions)
+            .filter(|(_, d)| !d.boss && d.min_wave <= wave)
+            .map(|(h, d)| (h, d.weight))
+            .collect();
+        unlocked
+            .choose_weighted(rng, |(_, w)| *w)
+            .ok()
+            .map(|(h, _)| h.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaling_adds_then_multiplies() {
+        let formula = ScalingFormula {
+            add_per_wave: 10.0,
+            mul_per_wave: 0.5,
+        };
+        assert_eq!(formula.apply(20.0, 0), 20.0);
+        assert_eq!(formula.apply(20.0, 1), 45.0);
+        assert_eq!(formula.apply(20.0, 2), 90.0);
+    }
+
+    #[test]
+    fn definition_file_uses_defaults() {
+        let file: EnemyDefinitionFile = ron::de::from_str(
+            r#"(name: "Grunt", sprite: "enemies/grunt.png", health: 20.0, speed: 50.0, gold: 5)"#,
+        )
+        .unwrap();
+        assert_eq!(file.leak_damage, ENEMY_PLAYER_DAMAGE);
+        assert_eq!(file.size, ENEMY_RADIUS);
+        assert_eq!(file.weight, 1.0);
+        assert!(file.abilities.is_empty());
+        assert_eq!(file.scaling.health, ScalingFormula::default());
+    }
+
+    #[test]
+    fn shipped_definitions_parse() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/archetypes");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let parsed = ron::de::from_str::<EnemyDefinitionFile>(&text);
+            assert!(parsed.is_ok(), "{path:?}: {parsed:?}");
+        }
+    }
+}
@@ -1,4 +1,17 @@
-use bevy::color::Color;
+use std::{marker::PhantomData, ops::Deref};
+
+use bevy::{
+    asset::{Asset, AssetEvent, AssetLoader, AssetServer, Assets, Handle, LoadContext, io::Reader},
+    color::Color,
+    ecs::{
+        event::EventReader,
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+    log::info,
+};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
 
 // Font
 pub static FONT: &str = "fonts/FiraCodeNerdFont-Regular.ttf";
@@ -10,6 +23,10 @@ pub static GOLD_IMAGE_ICON: &str = "ui/upkeep.png";
 pub static HEART_IMAGE: &str = "ui/status_icon_life.png";
 pub static BASE_TOWER: &str = "towers/base_tower.png";
 pub static ENEMY_FOLDER: &str = "enemies";
+pub static ARCHETYPE_FOLDER: &str = "archetypes";
+pub static WAVE_SCRIPT: &str = "waves/default.waves.ron";
+pub static ECONOMY_RULES: &str = "economy/default.economy.ron";
+pub static SYNERGY_RULES: &str = "synergy/default.synergy.ron";
 pub static ALIVE_ENEMIES_ICON: &str = "enemies/Tex_creature_97_t.png";
 
 // Colors
@@ -22,6 +39,27 @@ pub static DEFAULT_HEX_COLOR: Color = Color::hsla(0.0, 1.0, 0.95, 1.0);
 pub static PATH_START_COLOR: Color = Color::hsla(0.8, 0.78, 0.3, 1.0);
 pub static PATH_END_COLOR: Color = Color::hsla(0.8, 0.78, 0.4, 1.0);
 pub static PATH_COLOR: Color = Color::hsla(0.3, 0.5, 0.8, 1.0);
+pub static PATH_PREVIEW_COLOR: Color = Color::hsla(50.0, 0.9, 0.6, 0.35);
+pub static PATH_ARROW_COLOR: Color = Color::hsla(50.0, 0.9, 0.6, 0.9);
+/// World units between two arrows of the path preview.
+pub static PATH_ARROW_SPACING: f32 = 120.0;
+pub static PATH_ARROW_SPEED: f32 = 60.0;
+pub static BASIC_TOWER_COLOR: Color = Color::WHITE;
+pub static SUPPORT_TOWER_COLOR: Color = Color::hsla(50.0, 0.9, 0.6, 1.0);
+pub static FROST_TOWER_COLOR: Color = Color::hsla(195.0, 0.9, 0.6, 1.0);
+pub static CURSE_TOWER_COLOR: Color = Color::hsla(285.0, 0.7, 0.5, 1.0);
+pub static FLAK_TOWER_COLOR: Color = Color::hsla(30.0, 0.9, 0.55, 1.0);
+pub static SYNERGY_LINK_COLOR: Color = Color::hsla(160.0, 0.8, 0.5, 1.0);
+pub static ELITE_LINK_COLOR: Color = Color::hsla(45.0, 1.0, 0.55, 1.0);
+pub static RANK_BADGE_COLOR: Color = Color::hsla(45.0, 1.0, 0.5, 1.0);
+pub static BOSS_BAR_COLOR: Color = Color::hsla(0.0, 0.8, 0.45, 1.0);
+pub static BOSS_SHIELD_COLOR: Color = Color::hsla(210.0, 0.8, 0.6, 1.0);
+pub static BOSS_BAR_BACKGROUND_COLOR: Color = Color::hsla(0.0, 0.0, 0.1, 0.8);
+pub static HEALTH_BAR_COLOR: Color = Color::hsla(120.0, 0.7, 0.45, 1.0);
+pub static HEALTH_BAR_BACKGROUND_COLOR: Color = Color::hsla(0.0, 0.0, 0.1, 0.8);
+pub static PHYSICAL_DAMAGE_COLOR: Color = Color::hsla(0.0, 0.0, 0.95, 1.0);
+pub static EXPLOSIVE_DAMAGE_COLOR: Color = Color::hsla(30.0, 1.0, 0.6, 1.0);
+pub static ABSORBED_DAMAGE_COLOR: Color = Color::hsla(210.0, 0.8, 0.7, 1.0);
 
 //Sounds
 pub static MAIN_LOOP: &str = "music/main_loop.wav";
@@ -29,6 +67,10 @@ pub static SHOT_SOUND: &str = "music/shot.wav";
 
 //Enemy
 pub static ENEMY_RADIUS: f32 = 10.0;
+/// Spline samples per path segment used for enemy movement.
+pub static PATH_SPLINE_SAMPLES: usize = 8;
+pub static GROUND_ENEMY_Z: f32 = 10.0;
+pub static AIR_ENEMY_Z: f32 = 12.0;
 pub static ENEMY_PLAYER_DAMAGE: f32 = 1.0;
 
 //Projectile
@@ -37,4 +79,124 @@ pub static PROJECTILE_SPEED: f32 = 650.0;
 
 //Player
 pub static PLAYER_INITIAL_GOLD: u32 = 100;
-pub static TOWER_COST: u32 = 20;
+pub static PLAYER_INITIAL_HEALTH: f32 = 10.0;
+/// Seconds to build before a wave starts on its own.
+pub static BUILD_PHASE_SECONDS: f32 = 20.0;
+/// Gold per second left on the countdown when a wave is started early.
+pub static EARLY_START_GOLD_PER_SECOND: f32 = 2.0;
+
+//Score
+pub static HIGH_SCORE_FILE: &str = "highscores.ron";
+/// Entries kept per map and difficulty.
+pub static HIGH_SCORES_PER_TABLE: usize = 10;
+/// Points for every gold coin dropped by killed enemies.
+pub static SCORE_PER_KILL_GOLD: u32 = 10;
+pub static SCORE_PER_WAVE: u32 = 100;
+pub static SCORE_PER_LIFE: u32 = 50;
+/// Seconds per cleared wave before the time bonus runs out.
+pub static SCORE_PAR_SECONDS_PER_WAVE: f32 = 90.0;
+pub static SCORE_TIME_BONUS_PER_SECOND: f32 = 2.0;
+
+//Save
+/// Run saved at the start of every build phase, resumed from the main menu.
+pub static SAVE_FILE: &str = "savegame.ron";
+/// Commands of the latest run, written at every wave and when the run ends.
+pub static REPLAY_FILE: &str = "replay.ron";
+
+//Endless
+/// Enemy budget of the first wave, in gold value of the picked archetypes.
+pub static ENDLESS_BASE_BUDGET: f32 = 60.0;
+pub static ENDLESS_BUDGET_PER_WAVE: f32 = 15.0;
+/// Compounding budget growth per wave.
+pub static ENDLESS_BUDGET_GROWTH: f32 = 0.05;
+/// Every n-th generated wave gets a mutator.
+pub static ENDLESS_MUTATOR_EVERY: u32 = 3;
+pub static ENDLESS_SPAWN_INTERVAL: f32 = 0.6;
+pub static ENDLESS_GROUP_DELAY: f32 = 2.0;
+pub static HASTE_SPEED: f32 = 1.5;
+pub static ARMORED_WAVE_ARMOR: f32 = 20.0;
+
+#[derive(Debug, Error)]
+pub enum RonAssetLoaderError {
+    #[error("could not read asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse asset: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// Reads all of `reader` as a RON encoded `T`.
+pub async fn read_ron<T: DeserializeOwned>(
+    reader: &mut dyn Reader,
+) -> Result<T, RonAssetLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    Ok(ron::de::from_bytes(&bytes)?)
+}
+
+/// Loads a `T` stored as a single RON file, registered once per asset type with
+/// the extensions it owns.
+pub struct RonAssetLoader<T> {
+    extensions: &'static [&'static str],
+    asset: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            asset: PhantomData,
+        }
+    }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonAssetLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = RonAssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        read_ron(reader).await
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// Run condition for the asset behind the handle resource `H`.
+pub fn is_loaded<H, A>(handle: Res<H>, asset_server: Res<AssetServer>) -> bool
+where
+    H: Resource + Deref<Target = Handle<A>>,
+    A: Asset,
+{
+    asset_server.is_loaded_with_dependencies(handle.id())
+}
+
+/// Copies the asset behind `H` into its resource whenever it is loaded or
+/// modified on disk.
+pub fn apply_loaded<H, A>(
+    mut events: EventReader<AssetEvent<A>>,
+    handle: Option<Res<H>>,
+    assets: Res<Assets<A>>,
+    mut resource: ResMut<A>,
+) where
+    H: Resource + Deref<Target = Handle<A>>,
+    A: Asset + Resource + Clone,
+{
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in events.read() {
+        if (event.is_loaded_with_dependencies(handle.id()) || event.is_modified(handle.id()))
+            && let Some(loaded) = assets.get(handle.id())
+        {
+            info!("applying {}", A::short_type_path());
+            *resource = loaded.clone();
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Changed, With},
+        removal_detection::RemovedComponents,
+        schedule::IntoScheduleConfigs,
+        system::{Query, Res},
+    },
+    platform::collections::HashMap,
+};
+
+use crate::{
+    DuringWave,
+    enemy::Enemy,
+    grid::HexSpatialGrid,
+    stats::StatBonus,
+    synergy::SynergyBonus,
+    tower::{Tower, TowerIndex},
+};
+
+pub struct AuraPlugin;
+
+impl Plugin for AuraPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(FixedUpdate, apply_support_auras);
+        app.add_systems(FixedUpdate, apply_debuff_auras.in_set(DuringWave));
+    }
+}
+
+/// Buffs every tower within `radius` hexes of the support tower.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SupportAura {
+    pub radius: i32,
+    pub bonus: StatBonus,
+}
+
+/// Slows and weakens every enemy within `radius` hexes of the tower.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DebuffAura {
+    pub radius: i32,
+    pub slow: f32,
+    pub vulnerability: f32,
+}
+
+/// Sum of all support auras currently affecting a tower.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AuraBonus(pub StatBonus);
+
+/// Strongest debuffs currently affecting an enemy, recomputed every frame.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct EnemyDebuff {
+    pub slow: f32,
+    pub vulnerability: f32,
+}
+
+impl EnemyDebuff {
+    pub fn speed_factor(&self) -> f32 {
+        (1.0 - self.slow).max(0.0)
+    }
+
+    pub fn damage_factor(&self) -> f32 {
+        1.0 + self.vulnerability
+    }
+}
+
+/// Recomputes `AuraBonus` for all towers whenever a tower is placed, moved or removed.
+pub fn apply_support_auras(
+    moved: Query<(), (With<Tower>, Changed<TowerIndex>)>,
+    mut removed: RemovedComponents<Tower>,
+    towers: Query<(Entity, &TowerIndex, &mut AuraBonus)>,
+    auras: Query<(Entity, &TowerIndex, &SupportAura)>,
+) {
+    if moved.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+    for (tower, index, mut bonus) in towers {
+        let total = auras
+            .iter()
+            .filter(|(source, source_index, aura)| {
+                *source != tower && source_index.distance(index) <= aura.radius
+            })
+            .fold(StatBonus::default(), |acc, (_, _, aura)| acc + aura.bonus);
+        if bonus.0 != total {
+            bonus.0 = total;
+        }
+    }
+}
+
+pub fn apply_debuff_auras(
+    enemies: Query<(Entity, &mut EnemyDebuff), With<Enemy>>,
+    auras: Query<(&TowerIndex, &DebuffAura, &SynergyBonus)>,
+    spatial_grid: Res<HexSpatialGrid>,
+) {
+    let mut debuffs = HashMap::<Entity, EnemyDebuff>::new();
+    for (index, aura, synergy) in auras {
+        let slow = aura.slow * (1.0 + synergy.0.slow);
+        let vulnerability = aura.vulnerability * (1.0 + synergy.0.vulnerability);
+        for enemy in spatial_grid.get_within(index, aura.radius) {
+            let debuff = debuffs.entry(enemy).or_default();
+            debuff.slow = debuff.slow.max(slow);
+            debuff.vulnerability = debuff.vulnerability.max(vulnerability);
+        }
+    }
+    for (e, mut debuff) in enemies {
+        *debuff = debuffs.get(&e).copied().unwrap_or_default();
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::{
+    app::{FixedUpdate, Plugin},
+    asset::{Assets, LoadedFolder},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Has, With},
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Query, Res},
+    },
+    log::{error, info},
+    math::Vec3Swizzles,
+    transform::components::Transform,
+};
+use serde::Deserialize;
+
+use crate::{
+    DuringWave,
+    archetype::{EnemyArchetypeFolder, EnemyDefinition},
+    enemy::{
+        Enemy, EnemyCurrentTarget, OnSecondPath, PathProgress, Shield, SpawnLocation,
+        spawn_archetype,
+    },
+    stats::{Health, MaxHealth, Speed, Wave},
+    wave::EnemyModifiers,
+};
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(FixedUpdate, update_boss_phases.in_set(DuringWave));
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum PhaseAction {
+    /// Spawns `count` enemies of the named archetype at the boss position.
+    Summon { enemy: String, count: u32 },
+    /// Absorbs the next `amount` damage.
+    Shield { amount: f32 },
+    /// Multiplies the boss speed.
+    SpeedUp { factor: f32 },
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BossPhase {
+    /// Fraction of max health at which the phase starts, `0.5` meaning 50%.
+    pub threshold: f32,
+    pub actions: Vec<PhaseAction>,
+}
+
+#[derive(Component)]
+pub struct Boss;
+
+/// Phases of a boss that have not been triggered yet, highest threshold first.
+#[derive(Component, Debug, Clone)]
+pub struct BossPhases {
+    pending: Vec<BossPhase>,
+}
+
+impl BossPhases {
+    pub fn new(phases: &[BossPhase]) -> Self {
+        let mut pending = phases.to_vec();
+        pending.sort_by(|a, b| a.threshold.total_cmp(&b.threshold));
+        Self { pending }
+    }
+
+    /// Removes and returns every phase whose threshold has been reached.
+    pub fn reached(&mut self, health_fraction: f32) -> Vec<BossPhase> {
+        let mut reached = vec![];
+        while let Some(phase) = self.pending.last()
+            && health_fraction <= phase.threshold
+        {
+            reached.push(self.pending.pop().unwrap());
+        }
+        reached
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_boss_phases(
+    mut commands: Commands,
+    bosses: Query<
+        (
+            Entity,
+            &mut BossPhases,
+            &Health,
+            &MaxHealth,
+            &mut Speed,
+            &Transform,
+            &EnemyCurrentTarget,
+            &PathProgress,
+            Has<OnSecondPath>,
+        ),
+        (With<Boss>, With<Enemy>),
+    >,
+    wave: Res<Wave>,
+    archetypes: Res<EnemyArchetypeFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<EnemyDefinition>>,
+) {
+    for (
+        boss,
+        mut phases,
+        health,
+        max_health,
+        mut speed,
+        transform,
+        target,
+        progress,
+        second_path,
+    ) in bosses
+    {
+        if health.0 <= 0.0 {
+            continue;
+        }
+        for phase in phases.reached(health.0 / max_health.0) {
+            info!("boss entered phase at {:.0}%", phase.threshold * 100.0);
+            for action in phase.actions {
+                match action {
+                    PhaseAction::Summon { enemy, count } => {
+                        let Some(handle) = archetypes.find(&enemy, &folders, &definitions) else {
+                            error!("unknown enemy archetype {enemy}");
+                            continue;
+                        };
+                        let definition = definitions.get(&handle).unwrap();
+                        for _ in 0..count {
+                            spawn_archetype(
+                                &mut commands,
+                                handle.clone(),
+                                definition,
+                                wave.0,
+                                &EnemyModifiers::default(),
+                                SpawnLocation {
+                                    position: transform.translation.xy(),
+                                    target: target.0,
+                                    progress: progress.0,
+                                    second_path,
+                                },
+                            );
+                        }
+                    }
+                    PhaseAction::Shield { amount } => {
+                        commands.entity(boss).insert(Shield(amount));
+                    }
+                    PhaseAction::SpeedUp { factor } => {
+                        speed.0 *= factor;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(threshold: f32) -> BossPhase {
+        BossPhase {
+            threshold,
+            actions: vec![PhaseAction::SpeedUp { factor: 2.0 }],
+        }
+    }
+
+    #[test]
+    fn phases_trigger_once_in_order() {
+        let mut phases = BossPhases::new(&[phase(0.25), phase(0.75), phase(0.5)]);
+        assert!(phases.reached(0.9).is_empty());
+        let reached = phases.reached(0.5);
+        assert_eq!(reached.len(), 2);
+        assert_eq!(reached[0].threshold, 0.75);
+        assert_eq!(reached[1].threshold, 0.5);
+        assert!(phases.reached(0.5).is_empty());
+        assert_eq!(phases.reached(0.1).len(), 1);
+    }
+}
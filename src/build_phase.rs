@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+
+use crate::{
+    BeforeWave, GameState,
+    assets::{BUILD_PHASE_SECONDS, EARLY_START_GOLD_PER_SECOND, FONT_SIZE},
+    command::PlayerCommand,
+    difficulty::Difficulty,
+    economy::LedgerReason,
+    endless::{GeneratedWave, wave_definition},
+    grid::PathStart,
+    path_ready,
+    player::GoldGained,
+    stats::Wave,
+    ui::UiFont,
+    wave::{WaveDefinition, WaveScript, WaveScriptHandle},
+};
+
+pub struct BuildPhasePlugin;
+
+impl Plugin for BuildPhasePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(start_wave_early);
+        app.add_systems(OnEnter(GameState::BeforeWave), start_build_phase);
+        app.add_systems(FixedUpdate, tick_build_phase.in_set(BeforeWave));
+    }
+}
+
+/// Countdown panel and the key to start the wave early.
+pub struct BuildPhaseUiPlugin;
+
+impl Plugin for BuildPhaseUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            spawn_build_phase_panel.after(start_build_phase),
+        );
+        app.add_systems(
+            Update,
+            (start_wave_on_key, update_build_phase_panel).in_set(BeforeWave),
+        );
+    }
+}
+
+/// Countdown before the next wave starts on its own.
+#[derive(Resource, Deref, DerefMut)]
+pub struct BuildPhase(pub Timer);
+
+/// Starts the next wave before the countdown ends, paying out a bonus.
+#[derive(Event)]
+pub struct StartWaveEarly;
+
+#[derive(Component)]
+pub struct CountdownLabel;
+#[derive(Component)]
+pub struct StartWaveLabel;
+
+/// Gold paid for starting a wave with `remaining_secs` left on the countdown.
+pub fn early_start_bonus(remaining_secs: f32) -> u32 {
+    (remaining_secs.max(0.0) * EARLY_START_GOLD_PER_SECOND).floor() as u32
+}
+
+/// Enemy counts of a wave by archetype, in order of first appearance.
+pub fn wave_preview(wave: &WaveDefinition) -> Vec<(String, u32)> {
+    let mut preview: Vec<(String, u32)> = vec![];
+    for group in &wave.groups {
+        match preview.iter_mut().find(|(enemy, _)| *enemy == group.enemy) {
+            Some((_, count)) => *count += group.count,
+            None => preview.push((group.enemy.clone(), group.count)),
+        }
+    }
+    preview
+}
+
+pub fn start_build_phase(mut commands: Commands) {
+    commands.insert_resource(BuildPhase(Timer::from_seconds(
+        BUILD_PHASE_SECONDS,
+        TimerMode::Once,
+    )));
+}
+
+pub fn tick_build_phase(
+    mut phase: ResMut<BuildPhase>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+    path: Query<(), With<PathStart>>,
+) {
+    phase.tick(time.delta());
+    if phase.finished() && path_ready(path) {
+        info!("build phase over");
+        next_state.set(GameState::Wave);
+    }
+}
+
+pub fn start_wave_on_key(mut commands: Commands, keys: Res<ButtonInput<KeyCode>>) {
+    if keys.just_pressed(KeyCode::Enter) {
+        commands.trigger(PlayerCommand::StartWave);
+    }
+}
+
+pub fn start_wave_early(
+    _trigger: Trigger<StartWaveEarly>,
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    phase: Option<Res<BuildPhase>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    path: Query<(), With<PathStart>>,
+) {
+    if *state.get() != GameState::BeforeWave || !path_ready(path) {
+        return;
+    }
+    let Some(phase) = phase else {
+        return;
+    };
+    let bonus = early_start_bonus(phase.remaining_secs());
+    info!("starting wave early for {bonus} gold");
+    if bonus > 0 {
+        commands.trigger(GoldGained {
+            amount: bonus,
+            reason: LedgerReason::EarlyStart,
+        });
+    }
+    next_state.set(GameState::Wave);
+}
+
+pub fn spawn_build_phase_panel(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    wave: Res<Wave>,
+    script: Res<WaveScriptHandle>,
+    scripts: Res<Assets<WaveScript>>,
+    difficulty: Res<Difficulty>,
+    generated: Option<Res<GeneratedWave>>,
+) {
+    let text_font = TextFont::default()
+        .with_font(font.0.clone())
+        .with_font_size(FONT_SIZE);
+    let mutator = generated
+        .as_deref()
+        .filter(|g| g.wave == wave.0)
+        .and_then(|g| g.mutator);
+    let preview = wave_definition(wave.0, scripts.get(&script.0), generated.as_deref())
+        .map(|w| wave_preview(&difficulty.settings().scale_wave(&w)))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(enemy, count)| format!("{count}x {enemy}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(30.0),
+                width: Val::Percent(40.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            BackgroundColor(Color::hsla(0.0, 0.0, 0.0, 0.6)),
+            StateScoped(GameState::BeforeWave),
+        ))
+        .with_children(|builder| {
+            builder.spawn((Text::new(""), text_font.clone(), CountdownLabel));
+            builder.spawn((
+                Text::new(format!("next: {preview}")),
+                text_font.clone().with_font_size(FONT_SIZE * 0.8),
+            ));
+            if let Some(mutator) = mutator {
+                builder.spawn((
+                    Text::new(format!("mutator: {}", mutator.description())),
+                    text_font.clone().with_font_size(FONT_SIZE * 0.8),
+                    TextColor(Color::hsl(20.0, 0.9, 0.6)),
+                ));
+            }
+            builder
+                .spawn((
+                    Node {
+                        padding: UiRect::axes(Val::Px(12.0), Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    Button,
+                    BackgroundColor(Color::hsla(120.0, 0.4, 0.25, 1.0)),
+                ))
+                .with_child((
+                    Text::new(""),
+                    text_font.clone(),
+                    StartWaveLabel,
+                    Pickable::IGNORE,
+                ))
+                .observe(|_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(PlayerCommand::StartWave);
+                });
+        });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_build_phase_panel(
+    phase: Res<BuildPhase>,
+    wave: Res<Wave>,
+    mut countdown: Query<&mut Text, (With<CountdownLabel>, Without<StartWaveLabel>)>,
+    mut start: Query<&mut Text, (With<StartWaveLabel>, Without<CountdownLabel>)>,
+) {
+    let remaining = phase.remaining_secs();
+    for mut text in &mut countdown {
+        text.0 = format!("wave {} in {:.0}s", wave.0 + 1, remaining.ceil());
+    }
+    for mut text in &mut start {
+        text.0 = format!(
+            "Start wave now (+{} gold) [Enter]",
+            early_start_bonus(remaining)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wave::WaveGroup;
+
+    #[test]
+    fn preview_merges_groups_of_the_same_enemy() {
+        let wave = WaveDefinition {
+            groups: vec![
+                WaveGroup::new("Grunt", 10),
+                WaveGroup::new("Runner", 5),
+                WaveGroup::new("Grunt", 3),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            wave_preview(&wave),
+            vec![("Grunt".to_string(), 13), ("Runner".to_string(), 5)]
+        );
+    }
+
+    #[test]
+    fn bonus_scales_with_remaining_time() {
+        assert_eq!(early_start_bonus(0.0), 0);
+        assert_eq!(early_start_bonus(-1.0), 0);
+        assert!(early_start_bonus(BUILD_PHASE_SECONDS) > early_start_bonus(5.0));
+    }
+}
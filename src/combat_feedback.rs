@@ -0,0 +1,222 @@
+use bevy::{
+    app::{Plugin, Update},
+    color::Alpha,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        hierarchy::Children,
+        observer::Trigger,
+        query::{Added, Changed, With},
+        schedule::{IntoScheduleConfigs, common_conditions::resource_changed},
+        spawn::SpawnRelated,
+        system::{Commands, Query, Res},
+    },
+    math::Vec2,
+    render::view::Visibility,
+    sprite::{Anchor, Sprite},
+    text::{Text2d, TextColor, TextFont},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+use rand::random_range;
+
+use crate::{
+    assets::{ABSORBED_DAMAGE_COLOR, FONT_SIZE, HEALTH_BAR_BACKGROUND_COLOR, HEALTH_BAR_COLOR},
+    enemy::{DamageDealt, Enemy, EnemySize},
+    settings::Settings,
+    stats::{Health, MaxHealth},
+    ui::UiFont,
+};
+
+pub struct CombatFeedbackPlugin;
+
+impl Plugin for CombatFeedbackPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_observer(spawn_damage_numbers);
+        app.add_systems(
+            Update,
+            (
+                spawn_health_bars,
+                update_health_bars,
+                toggle_health_bars.run_if(resource_changed::<Settings>),
+                update_floating_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+static HEALTH_BAR_HEIGHT: f32 = 4.0;
+static HEALTH_BAR_OFFSET: f32 = 6.0;
+static DAMAGE_NUMBER_LIFETIME: f32 = 0.8;
+static DAMAGE_NUMBER_SPEED: f32 = 40.0;
+
+#[derive(Component)]
+pub struct HealthBar;
+#[derive(Component)]
+pub struct HealthBarFill;
+
+/// Text that rises and fades out before despawning.
+#[derive(Component)]
+pub struct FloatingText {
+    pub lifetime: Timer,
+    pub velocity: Vec2,
+}
+
+pub fn health_fraction(health: &Health, max_health: &MaxHealth) -> f32 {
+    if max_health.0 <= 0.0 {
+        0.0
+    } else {
+        (health.0 / max_health.0).clamp(0.0, 1.0)
+    }
+}
+
+fn bar_visibility(settings: &Settings) -> Visibility {
+    if settings.health_bars {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+pub fn spawn_health_bars(
+    mut commands: Commands,
+    enemies: Query<(Entity, &EnemySize), Added<Enemy>>,
+    settings: Res<Settings>,
+) {
+    for (enemy, size) in enemies {
+        let width = size.0 * 2.0;
+        commands.entity(enemy).with_child((
+            HealthBar,
+            Sprite::from_color(
+                HEALTH_BAR_BACKGROUND_COLOR,
+                Vec2::new(width, HEALTH_BAR_HEIGHT),
+            ),
+            Transform::from_xyz(0.0, size.0 + HEALTH_BAR_OFFSET, 1.0),
+            bar_visibility(&settings),
+            Children::spawn_one((
+                HealthBarFill,
+                Sprite {
+                    color: HEALTH_BAR_COLOR,
+                    custom_size: Some(Vec2::new(width, HEALTH_BAR_HEIGHT)),
+                    anchor: Anchor::CenterLeft,
+                    ..Default::default()
+                },
+                Transform::from_xyz(-width / 2.0, 0.0, 0.1),
+            )),
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_health_bars(
+    enemies: Query<(&Health, &MaxHealth, &Children), (With<Enemy>, Changed<Health>)>,
+    bars: Query<&Children, With<HealthBar>>,
+    mut fills: Query<&mut Transform, With<HealthBarFill>>,
+) {
+    for (health, max_health, children) in enemies {
+        let fraction = health_fraction(health, max_health);
+        for bar in bars.iter_many(children) {
+            let mut iter = fills.iter_many_mut(bar);
+            while let Some(mut fill) = iter.fetch_next() {
+                fill.scale.x = fraction;
+            }
+        }
+    }
+}
+
+pub fn toggle_health_bars(settings: Res<Settings>, bars: Query<&mut Visibility, With<HealthBar>>) {
+    for mut visibility in bars {
+        *visibility = bar_visibility(&settings);
+    }
+}
+
+pub fn spawn_damage_numbers(
+    trigger: Trigger<DamageDealt>,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    font: Res<UiFont>,
+) {
+    if !settings.damage_numbers {
+        return;
+    }
+    let hit = trigger.event();
+    let numbers = [
+        (hit.amount, format!("{:.0}", hit.amount), hit.kind.color()),
+        (
+            hit.absorbed,
+            format!("({:.0})", hit.absorbed),
+            ABSORBED_DAMAGE_COLOR,
+        ),
+    ];
+    for (amount, text, color) in numbers {
+        if amount < 0.5 {
+            continue;
+        }
+        let position = hit.position + Vec2::new(random_range(-8.0..=8.0), 8.0);
+        commands.spawn((
+            FloatingText {
+                lifetime: Timer::from_seconds(DAMAGE_NUMBER_LIFETIME, TimerMode::Once),
+                velocity: Vec2::new(0.0, DAMAGE_NUMBER_SPEED),
+            },
+            Text2d::new(text),
+            TextFont::default()
+                .with_font(font.0.clone())
+                .with_font_size(FONT_SIZE * 0.8),
+            TextColor(color),
+            Transform::from_xyz(position.x, position.y, 20.0),
+        ));
+    }
+}
+
+pub fn update_floating_text(
+    mut commands: Commands,
+    texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text, mut transform, mut color) in texts {
+        text.lifetime.tick(time.delta());
+        if text.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += text.velocity.extend(0.0) * time.delta_secs();
+        let alpha = 1.0 - text.lifetime.fraction();
+        color.0 = color.0.with_alpha(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{MinimalPlugins, app::App};
+
+    use super::*;
+
+    #[test]
+    fn health_bar_follows_health() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.init_resource::<Settings>();
+        app.add_systems(Update, (spawn_health_bars, update_health_bars).chain());
+        let enemy = app
+            .world_mut()
+            .spawn((Enemy, EnemySize(10.0), Health(40.0), MaxHealth(40.0)))
+            .id();
+        app.update();
+        app.world_mut().get_mut::<Health>(enemy).unwrap().0 = 10.0;
+        app.update();
+
+        let mut fills = app
+            .world_mut()
+            .query_filtered::<&Transform, With<HealthBarFill>>();
+        let fill = fills.single(app.world()).unwrap();
+        assert_eq!(fill.scale.x, 0.25);
+    }
+
+    #[test]
+    fn health_fraction_is_clamped() {
+        assert_eq!(health_fraction(&Health(-5.0), &MaxHealth(10.0)), 0.0);
+        assert_eq!(health_fraction(&Health(15.0), &MaxHealth(10.0)), 1.0);
+        assert_eq!(health_fraction(&Health(5.0), &MaxHealth(0.0)), 0.0);
+    }
+}
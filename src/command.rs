@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build_phase::StartWaveEarly,
+    economy::{EconomyRules, GoldSpent, LedgerReason},
+    grid::{GridEntity, GridEntry, GridIndex, HexGridRenderRadius, HexHashGrid},
+    path::regeneration::FreeRelocation,
+    player::{Gold, GoldGained, Player},
+    stats::RunRng,
+    tower::{BaseTowerImage, Tower, TowerIndex, TowerKind, spawn_tower_at},
+};
+
+/// Everything a player can do to change the course of a run. Input is turned
+/// into these so runs can be recorded and replayed.
+#[derive(Event, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerCommand {
+    PlaceTower {
+        index: GridIndex,
+        kind: TowerKind,
+    },
+    SellTower {
+        index: GridIndex,
+    },
+    /// Moves a tower that lost its reach to the path, see `FreeRelocation`.
+    MoveTower {
+        from: GridIndex,
+        to: GridIndex,
+    },
+    StartWave,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn apply_player_command(
+    In(command): In<PlayerCommand>,
+    mut commands: Commands,
+    mut hex_grid: ResMut<HexHashGrid>,
+    mut player_gold: Single<&mut Gold, With<Player>>,
+    tiles: Query<(Entity, &GridEntity)>,
+    towers: Query<(Entity, &TowerIndex, &TowerKind, Has<FreeRelocation>), With<Tower>>,
+    base_tower_image: Res<BaseTowerImage>,
+    size: Res<HexGridRenderRadius>,
+    economy: Res<EconomyRules>,
+    mut rng: ResMut<RunRng>,
+) {
+    let tile_at = |index: GridIndex| tiles.iter().find(|(_, t)| t.0 == index).map(|(e, _)| e);
+    let tower_at = |index: GridIndex| towers.iter().find(|(_, i, ..)| i.0 == index);
+    match command {
+        PlayerCommand::PlaceTower { index, kind } => {
+            let Some(tile) = tile_at(index) else {
+                return;
+            };
+            if hex_grid[index] == GridEntry::None
+                && spawn_tower_at(
+                    tile,
+                    index,
+                    kind,
+                    commands.reborrow(),
+                    base_tower_image,
+                    &Gold(economy.tower_cost(kind)),
+                    &mut player_gold,
+                    **size,
+                    &mut rng.0,
+                )
+            {
+                info!("set tower: {:?}", index);
+                commands.trigger(GoldSpent {
+                    amount: economy.tower_cost(kind),
+                    reason: LedgerReason::Build,
+                });
+                hex_grid[index] = GridEntry::Tower;
+            }
+        }
+        PlayerCommand::SellTower { index } => {
+            let Some((tower, _, kind, _)) = tower_at(index) else {
+                return;
+            };
+            commands.entity(tower).despawn();
+            commands.trigger(GoldGained {
+                amount: economy.sell_value(*kind),
+                reason: LedgerReason::Sale,
+            });
+            info!("sold tower: {:?}", index);
+            hex_grid[index] = GridEntry::None;
+        }
+        PlayerCommand::MoveTower { from, to } => {
+            let (Some((tower, .., true)), Some(tile)) = (tower_at(from), tile_at(to)) else {
+                return;
+            };
+            if hex_grid[to] != GridEntry::None {
+                return;
+            }
+            commands
+                .entity(tower)
+                .insert((ChildOf(tile), TowerIndex(to)))
+                .remove::<FreeRelocation>();
+            info!("moved tower: {:?} -> {:?}", from, to);
+            hex_grid[from] = GridEntry::None;
+            hex_grid[to] = GridEntry::Tower;
+        }
+        PlayerCommand::StartWave => commands.trigger(StartWaveEarly),
+    }
+}
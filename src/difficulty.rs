@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    assets::{PLAYER_INITIAL_GOLD, PLAYER_INITIAL_HEALTH},
+    player::{Gold, Player},
+    stats::Health,
+    wave::{EnemyModifiers, WaveDefinition},
+};
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>();
+        app.add_observer(start_run);
+    }
+}
+
+/// Preset chosen in the new game menu.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// Normal stats with ever growing enemies and no final wave.
+    Endless,
+}
+
+/// Numbers a `Difficulty` scales.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DifficultySettings {
+    pub starting_gold: u32,
+    pub starting_health: f32,
+    /// Enemy health multiplier on the first wave.
+    pub enemy_health: f32,
+    /// Enemy speed multiplier on the first wave.
+    pub enemy_speed: f32,
+    /// Extra enemy health per wave, compounding.
+    pub health_growth: f32,
+    /// Extra enemy speed per wave, compounding.
+    pub speed_growth: f32,
+    /// Multiplier for the number of enemies in every wave group.
+    pub enemy_count: f32,
+    /// Whether the run goes on after the last scripted wave.
+    pub endless: bool,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Endless,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Endless => "Endless",
+        }
+    }
+
+    pub fn settings(&self) -> DifficultySettings {
+        let normal = DifficultySettings {
+            starting_gold: PLAYER_INITIAL_GOLD,
+            starting_health: PLAYER_INITIAL_HEALTH,
+            enemy_health: 1.0,
+            enemy_speed: 1.0,
+            health_growth: 0.0,
+            speed_growth: 0.0,
+            enemy_count: 1.0,
+            endless: false,
+        };
+        match self {
+            Difficulty::Easy => DifficultySettings {
+                starting_gold: PLAYER_INITIAL_GOLD * 3 / 2,
+                starting_health: PLAYER_INITIAL_HEALTH * 2.0,
+                enemy_health: 0.8,
+                enemy_speed: 0.9,
+                enemy_count: 0.75,
+                ..normal
+            },
+            Difficulty::Normal => normal,
+            Difficulty::Hard => DifficultySettings {
+                starting_gold: PLAYER_INITIAL_GOLD * 4 / 5,
+                starting_health: PLAYER_INITIAL_HEALTH / 2.0,
+                enemy_health: 1.25,
+                enemy_speed: 1.1,
+                health_growth: 0.03,
+                speed_growth: 0.01,
+                enemy_count: 1.25,
+                ..normal
+            },
+            Difficulty::Endless => DifficultySettings {
+                health_growth: 0.05,
+                speed_growth: 0.01,
+                endless: true,
+                ..normal
+            },
+        }
+    }
+}
+
+impl DifficultySettings {
+    /// `modifiers` with the difficulty's enemy scaling for `wave` applied.
+    pub fn enemy_modifiers(&self, modifiers: &EnemyModifiers, wave: u32) -> EnemyModifiers {
+        EnemyModifiers {
+            health: modifiers.health
+                * self.enemy_health
+                * (1.0 + self.health_growth).powi(wave as i32),
+            speed: modifiers.speed * self.enemy_speed * (1.0 + self.speed_growth).powi(wave as i32),
+            ..*modifiers
+        }
+    }
+
+    /// `wave` with every group's count scaled, keeping at least one enemy.
+    pub fn scale_wave(&self, wave: &WaveDefinition) -> WaveDefinition {
+        let mut wave = wave.clone();
+        for group in &mut wave.groups {
+            if group.count > 0 {
+                group.count = ((group.count as f32 * self.enemy_count).round() as u32).max(1);
+            }
+        }
+        wave
+    }
+}
+
+/// Starts a run with the current `Difficulty`.
+#[derive(Event)]
+pub struct StartRun;
+
+pub fn start_run(
+    _trigger: Trigger<StartRun>,
+    difficulty: Res<Difficulty>,
+    mut player: Query<(&mut Gold, &mut Health), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    info!("starting {} run", difficulty.name());
+    reset_player(&difficulty, &mut player);
+    next_state.set(GameState::BeforeWave);
+}
+
+/// Gives every player the starting gold and health of `difficulty`.
+pub fn reset_player(
+    difficulty: &Difficulty,
+    player: &mut Query<(&mut Gold, &mut Health), With<Player>>,
+) {
+    let settings = difficulty.settings();
+    for (mut gold, mut health) in player {
+        gold.0 = settings.starting_gold;
+        health.0 = settings.starting_health;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wave::WaveGroup;
+
+    #[test]
+    fn harder_presets_scale_up() {
+        let easy = Difficulty::Easy.settings();
+        let normal = Difficulty::Normal.settings();
+        let hard = Difficulty::Hard.settings();
+        assert!(easy.starting_gold > normal.starting_gold);
+        assert!(hard.starting_health < normal.starting_health);
+        let base = EnemyModifiers::default();
+        for wave in [0, 5, 20] {
+            let health = |s: &DifficultySettings| s.enemy_modifiers(&base, wave).health;
+            assert!(health(&easy) < health(&normal));
+            assert!(health(&normal) < health(&hard));
+        }
+        assert_eq!(normal.enemy_modifiers(&base, 9), base);
+        assert!(Difficulty::Endless.settings().endless);
+    }
+
+    #[test]
+    fn wave_counts_scale_but_keep_one_enemy() {
+        let wave = WaveDefinition {
+            groups: vec![
+                WaveGroup::new("Grunt", 8),
+                WaveGroup::new("Grunt", 1),
+                WaveGroup::new("Grunt", 0),
+            ],
+            ..Default::default()
+        };
+        let scaled = Difficulty::Easy.settings().scale_wave(&wave);
+        let counts: Vec<u32> = scaled.groups.iter().map(|g| g.count).collect();
+        assert_eq!(counts, vec![6, 1, 0]);
+    }
+}
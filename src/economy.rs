@@ -0,0 +1,297 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    app::{Plugin, Startup, Update},
+    asset::{Asset, AssetApp, AssetServer, Handle},
+    ecs::{
+        event::Event,
+        observer::Trigger,
+        query::With,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    platform::collections::HashMap,
+    prelude::Deref,
+    reflect::TypePath,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{ECONOMY_RULES, RonAssetLoader, apply_loaded},
+    player::{Gold, GoldGained, Player},
+    stats::{Health, Wave},
+    tower::TowerKind,
+};
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_asset::<EconomyRules>();
+        app.register_asset_loader(RonAssetLoader::<EconomyRules>::new(&["economy.ron"]));
+        app.init_resource::<EconomyRules>();
+        app.init_resource::<Ledger>();
+        app.add_event::<GoldSpent>();
+        app.add_observer(record_gold_gained);
+        app.add_observer(record_gold_spent);
+        app.add_systems(Startup, load_economy_rules);
+        app.add_systems(Update, apply_loaded::<EconomyRulesHandle, EconomyRules>);
+    }
+}
+
+/// Transactions kept in `Ledger::recent`.
+static LEDGER_HISTORY: usize = 6;
+
+/// Tunable numbers behind every gold formula, loaded from `ECONOMY_RULES`.
+#[derive(Asset, Resource, TypePath, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct EconomyRules {
+    /// Gold paid at the end of every wave.
+    pub wave_income: u32,
+    /// Extra end of wave gold for every wave completed so far.
+    pub wave_income_growth: u32,
+    /// Fraction of the banked gold paid out at the end of a wave.
+    pub interest_rate: f32,
+    /// Most interest paid for a single wave.
+    pub interest_cap: u32,
+    /// Fraction of the build cost refunded when selling a tower.
+    pub sell_refund: f32,
+    /// Cost of towers missing from `tower_costs`.
+    pub default_tower_cost: u32,
+    pub tower_costs: HashMap<TowerKind, u32>,
+}
+
+impl Default for EconomyRules {
+    fn default() -> Self {
+        Self {
+            wave_income: 10,
+            wave_income_growth: 2,
+            interest_rate: 0.05,
+            interest_cap: 10,
+            sell_refund: 0.5,
+            default_tower_cost: 20,
+            tower_costs: HashMap::from([
+                (TowerKind::Basic, 20),
+                (TowerKind::Support, 30),
+                (TowerKind::Frost, 25),
+                (TowerKind::Curse, 35),
+                (TowerKind::Flak, 30),
+            ]),
+        }
+    }
+}
+
+impl EconomyRules {
+    /// Gold paid once `completed_waves` waves have been survived.
+    pub fn wave_income(&self, completed_waves: u32) -> u32 {
+        self.wave_income + self.wave_income_growth * completed_waves.saturating_sub(1)
+    }
+
+    /// Interest on `banked` gold, rounded down and capped.
+    pub fn interest(&self, banked: u32) -> u32 {
+        ((banked as f32 * self.interest_rate.max(0.0)).floor() as u32).min(self.interest_cap)
+    }
+
+    pub fn tower_cost(&self, kind: TowerKind) -> u32 {
+        self.tower_costs
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.default_tower_cost)
+    }
+
+    pub fn sell_value(&self, kind: TowerKind) -> u32 {
+        (self.tower_cost(kind) as f32 * self.sell_refund.clamp(0.0, 1.0)) as u32
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct EconomyRulesHandle(pub Handle<EconomyRules>);
+
+/// Why gold changed hands.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerReason {
+    Kill,
+    WaveIncome,
+    Interest,
+    EarlyStart,
+    Sale,
+    Build,
+}
+
+impl LedgerReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerReason::Kill => "kills",
+            LedgerReason::WaveIncome => "wave income",
+            LedgerReason::Interest => "interest",
+            LedgerReason::EarlyStart => "early start",
+            LedgerReason::Sale => "sold",
+            LedgerReason::Build => "built",
+        }
+    }
+}
+
+/// Gold already taken from the player.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GoldSpent {
+    pub amount: u32,
+    pub reason: LedgerReason,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub reason: LedgerReason,
+    pub amount: i64,
+}
+
+/// Running record of the gold earned and spent during a run.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Ledger {
+    pub income: u32,
+    pub expenses: u32,
+    /// Latest transactions, newest last, with consecutive ones of the same
+    /// reason merged.
+    pub recent: VecDeque<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn record(&mut self, reason: LedgerReason, amount: i64) {
+        if amount >= 0 {
+            self.income += amount as u32;
+        } else {
+            self.expenses += amount.unsigned_abs() as u32;
+        }
+        match self.recent.back_mut() {
+            Some(last) if last.reason == reason => last.amount += amount,
+            _ => {
+                self.recent.push_back(LedgerEntry { reason, amount });
+                if self.recent.len() > LEDGER_HISTORY {
+                    self.recent.pop_front();
+                }
+            }
+        }
+    }
+
+    pub fn balance(&self) -> i64 {
+        self.income as i64 - self.expenses as i64
+    }
+}
+
+pub fn load_economy_rules(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EconomyRulesHandle(asset_server.load(ECONOMY_RULES)));
+}
+
+/// Pays the end of wave income and the interest on the banked gold.
+pub fn pay_wave_income(
+    mut commands: Commands,
+    rules: Res<EconomyRules>,
+    wave: Res<Wave>,
+    player: Query<(&Gold, &Health), With<Player>>,
+) {
+    let Ok((gold, health)) = player.single() else {
+        return;
+    };
+    if health.0 <= 0.0 {
+        return;
+    }
+    let payouts = [
+        (LedgerReason::Interest, rules.interest(gold.0)),
+        (LedgerReason::WaveIncome, rules.wave_income(wave.0)),
+    ];
+    for (reason, amount) in payouts {
+        if amount > 0 {
+            commands.trigger(GoldGained { amount, reason });
+        }
+    }
+}
+
+pub fn record_gold_gained(trigger: Trigger<GoldGained>, mut ledger: ResMut<Ledger>) {
+    let event = trigger.event();
+    ledger.record(event.reason, event.amount as i64);
+}
+
+pub fn record_gold_spent(trigger: Trigger<GoldSpent>, mut ledger: ResMut<Ledger>) {
+    let event = trigger.event();
+    ledger.record(event.reason, -(event.amount as i64));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wave_income_grows_with_completed_waves() {
+        let rules = EconomyRules {
+            wave_income: 10,
+            wave_income_growth: 3,
+            ..Default::default()
+        };
+        assert_eq!(rules.wave_income(0), 10);
+        assert_eq!(rules.wave_income(1), 10);
+        assert_eq!(rules.wave_income(4), 19);
+    }
+
+    #[test]
+    fn interest_is_rounded_down_and_capped() {
+        let rules = EconomyRules {
+            interest_rate: 0.1,
+            interest_cap: 5,
+            ..Default::default()
+        };
+        assert_eq!(rules.interest(0), 0);
+        assert_eq!(rules.interest(29), 2);
+        assert_eq!(rules.interest(50), 5);
+        assert_eq!(rules.interest(500), 5);
+    }
+
+    #[test]
+    fn tower_costs_fall_back_to_the_default() {
+        let rules = EconomyRules {
+            default_tower_cost: 15,
+            tower_costs: HashMap::from([(TowerKind::Flak, 40)]),
+            sell_refund: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(rules.tower_cost(TowerKind::Flak), 40);
+        assert_eq!(rules.tower_cost(TowerKind::Frost), 15);
+        assert_eq!(rules.sell_value(TowerKind::Flak), 20);
+        assert_eq!(rules.sell_value(TowerKind::Basic), 7);
+    }
+
+    #[test]
+    fn ledger_merges_consecutive_transactions() {
+        let mut ledger = Ledger::default();
+        ledger.record(LedgerReason::Kill, 3);
+        ledger.record(LedgerReason::Kill, 4);
+        ledger.record(LedgerReason::Build, -20);
+        ledger.record(LedgerReason::Kill, 2);
+        assert_eq!(ledger.income, 9);
+        assert_eq!(ledger.expenses, 20);
+        assert_eq!(ledger.balance(), -11);
+        let amounts: Vec<i64> = ledger.recent.iter().map(|e| e.amount).collect();
+        assert_eq!(amounts, vec![7, -20, 2]);
+
+        for _ in 0..LEDGER_HISTORY {
+            ledger.record(LedgerReason::Interest, 1);
+            ledger.record(LedgerReason::WaveIncome, 1);
+        }
+        assert_eq!(ledger.recent.len(), LEDGER_HISTORY);
+        assert_eq!(
+            ledger.recent.back().unwrap().reason,
+            LedgerReason::WaveIncome
+        );
+    }
+
+    #[test]
+    fn shipped_rules_parse() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/economy/default.economy.ron"
+        );
+        let text = std::fs::read_to_string(path).unwrap();
+        let rules: EconomyRules = ron::de::from_str(&text).unwrap();
+        for kind in TowerKind::ALL {
+            assert!(rules.tower_costs.contains_key(&kind));
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use bevy::{asset::LoadedFolder, prelude::*};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+use crate::{
+    GameState,
+    archetype::{EnemyArchetypeFolder, EnemyDefinition, ScalingFormula},
+    assets::{
+        ARMORED_WAVE_ARMOR, ENDLESS_BASE_BUDGET, ENDLESS_BUDGET_GROWTH, ENDLESS_BUDGET_PER_WAVE,
+        ENDLESS_GROUP_DELAY, ENDLESS_MUTATOR_EVERY, ENDLESS_SPAWN_INTERVAL, HASTE_SPEED,
+    },
+    build_phase::spawn_build_phase_panel,
+    difficulty::Difficulty,
+    stats::{RunSeed, Wave},
+    wave::{EnemyModifiers, SpawnPoint, WaveDefinition, WaveGroup, WaveScript, WaveScriptHandle},
+};
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            prepare_endless_wave.before(spawn_build_phase_panel),
+        );
+    }
+}
+
+/// Enemy budget of `wave`.
+pub fn endless_budget(wave: u32) -> f32 {
+    ScalingFormula {
+        add_per_wave: ENDLESS_BUDGET_PER_WAVE,
+        mul_per_wave: ENDLESS_BUDGET_GROWTH,
+    }
+    .apply(ENDLESS_BASE_BUDGET, wave)
+}
+
+/// Archetype the endless generator can spend its budget on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndlessCandidate {
+    pub name: String,
+    pub cost: f32,
+    pub weight: f32,
+}
+
+impl EndlessCandidate {
+    /// Costs the archetype by the gold it is worth on `wave` with `modifiers`.
+    pub fn from_definition(
+        definition: &EnemyDefinition,
+        wave: u32,
+        modifiers: &EnemyModifiers,
+    ) -> Self {
+        let stats = definition.stats_for_wave(wave).with_modifiers(modifiers);
+        Self {
+            name: definition.name.clone(),
+            cost: stats.gold.max(1) as f32,
+            weight: definition.weight,
+        }
+    }
+}
+
+/// Spends `budget` on weighted picks among the affordable candidates, one
+/// group per archetype in order of the first pick.
+pub fn generate_wave<R: Rng>(
+    rng: &mut R,
+    candidates: &[EndlessCandidate],
+    budget: f32,
+) -> WaveDefinition {
+    let mut remaining = budget;
+    let mut counts: Vec<(String, u32)> = vec![];
+    loop {
+        let affordable: Vec<&EndlessCandidate> = candidates
+            .iter()
+            .filter(|c| c.weight > 0.0 && c.cost > 0.0 && c.cost <= remaining)
+            .collect();
+        let Ok(pick) = affordable.choose_weighted(rng, |c| c.weight) else {
+            break;
+        };
+        remaining -= pick.cost;
+        match counts.iter_mut().find(|(name, _)| *name == pick.name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((pick.name.clone(), 1)),
+        }
+    }
+    WaveDefinition {
+        groups: counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (enemy, count))| WaveGroup {
+                enemy,
+                count,
+                interval: ENDLESS_SPAWN_INTERVAL,
+                delay: if i == 0 { 0.0 } else { ENDLESS_GROUP_DELAY },
+                spawn: SpawnPoint::Any,
+                modifiers: EnemyModifiers::default(),
+            })
+            .collect(),
+        second_path: false,
+    }
+}
+
+/// Twist applied to a whole generated wave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutator {
+    /// All enemies are faster.
+    Haste,
+    /// All enemies gain armor.
+    Armored,
+    /// Enemies also come from the start of a second path.
+    DoublePath,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 3] = [Mutator::Haste, Mutator::Armored, Mutator::DoublePath];
+
+    pub fn description(&self) -> String {
+        match self {
+            Mutator::Haste => format!("all enemies +{:.0}% speed", (HASTE_SPEED - 1.0) * 100.0),
+            Mutator::Armored => format!("armored wave: +{ARMORED_WAVE_ARMOR:.0} armor"),
+            Mutator::DoublePath => "double path: enemies also come from a second start".to_string(),
+        }
+    }
+
+    /// Changes the modifiers of a single enemy.
+    pub fn modify(&self, modifiers: &mut EnemyModifiers) {
+        match self {
+            Mutator::Haste => modifiers.speed *= HASTE_SPEED,
+            Mutator::Armored => modifiers.armor += ARMORED_WAVE_ARMOR,
+            Mutator::DoublePath => {}
+        }
+    }
+
+    pub fn apply(&self, wave: &mut WaveDefinition) {
+        for group in &mut wave.groups {
+            self.modify(&mut group.modifiers);
+        }
+        wave.second_path |= *self == Mutator::DoublePath;
+    }
+}
+
+/// Mutator for the `index`-th generated wave, if it gets one.
+pub fn mutator_for<R: Rng>(rng: &mut R, index: u32) -> Option<Mutator> {
+    if (index + 1).is_multiple_of(ENDLESS_MUTATOR_EVERY) {
+        Mutator::ALL.choose(rng).copied()
+    } else {
+        None
+    }
+}
+
+/// Wave generated once the script ran out in endless mode.
+#[derive(Resource, Clone, Debug)]
+pub struct GeneratedWave {
+    pub wave: u32,
+    pub definition: WaveDefinition,
+    pub mutator: Option<Mutator>,
+}
+
+/// Definition of `wave`, preferring a matching generated wave over the script.
+pub fn wave_definition(
+    wave: u32,
+    script: Option<&WaveScript>,
+    generated: Option<&GeneratedWave>,
+) -> Option<WaveDefinition> {
+    generated
+        .filter(|g| g.wave == wave)
+        .map(|g| g.definition.clone())
+        .or_else(|| script.and_then(|s| s.get(wave)).cloned())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_endless_wave(
+    mut commands: Commands,
+    wave: Res<Wave>,
+    difficulty: Res<Difficulty>,
+    script: Res<WaveScriptHandle>,
+    scripts: Res<Assets<WaveScript>>,
+    archetypes: Res<EnemyArchetypeFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<EnemyDefinition>>,
+    seed: Res<RunSeed>,
+) {
+    let scripted = scripts.get(&script.0).map_or(0, |s| s.waves.len()) as u32;
+    if !difficulty.settings().endless || wave.0 < scripted {
+        commands.remove_resource::<GeneratedWave>();
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(seed.for_wave(wave.0).rotate_left(17));
+    let mutator = mutator_for(&mut rng, wave.0 - scripted);
+    // Cost enemies at the value they will spawn with.
+    let mut modifiers = EnemyModifiers::default();
+    if let Some(mutator) = mutator {
+        mutator.modify(&mut modifiers);
+    }
+    let modifiers = difficulty.settings().enemy_modifiers(&modifiers, wave.0);
+    let candidates: Vec<EndlessCandidate> = archetypes
+        .definitions(&folders, &definitions)
+        .filter(|(_, d)| !d.boss && d.min_wave <= wave.0)
+        .map(|(_, d)| EndlessCandidate::from_definition(d, wave.0, &modifiers))
+        .collect();
+    let mut definition = generate_wave(&mut rng, &candidates, endless_budget(wave.0));
+    if let Some(mutator) = mutator {
+        mutator.apply(&mut definition);
+    }
+    info!("generated endless wave {} with {mutator:?}", wave.0);
+    commands.insert_resource(GeneratedWave {
+        wave: wave.0,
+        definition,
+        mutator,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::{archetype::WaveScaling, enemy::EnemyLayer};
+
+    fn candidate(name: &str, cost: f32, weight: f32) -> EndlessCandidate {
+        EndlessCandidate {
+            name: name.to_string(),
+            cost,
+            weight,
+        }
+    }
+
+    #[test]
+    fn budget_grows_every_wave() {
+        assert_eq!(endless_budget(0), ENDLESS_BASE_BUDGET);
+        for wave in 0..30 {
+            assert!(endless_budget(wave + 1) > endless_budget(wave));
+        }
+    }
+
+    #[test]
+    fn generated_wave_spends_the_budget_on_affordable_enemies() {
+        let candidates = [
+            candidate("Cheap", 5.0, 3.0),
+            candidate("Pricey", 40.0, 1.0),
+            candidate("Unpicked", 1.0, 0.0),
+            candidate("Unaffordable", 500.0, 10.0),
+        ];
+        let mut rng = StdRng::seed_from_u64(7);
+        for budget in [4.0, 50.0, 300.0] {
+            let wave = generate_wave(&mut rng, &candidates, budget);
+            let spent: f32 = wave
+                .groups
+                .iter()
+                .map(|g| {
+                    let c = candidates.iter().find(|c| c.name == g.enemy).unwrap();
+                    c.cost * g.count as f32
+                })
+                .sum();
+            assert!(spent <= budget);
+            assert!(budget - spent < 5.0);
+            assert!(
+                wave.groups
+                    .iter()
+                    .all(|g| g.enemy == "Cheap" || g.enemy == "Pricey")
+            );
+        }
+    }
+
+    #[test]
+    fn candidates_cost_their_scaled_gold() {
+        let definition = EnemyDefinition {
+            name: "Grunt".to_string(),
+            sprite: Handle::default(),
+            tint: Color::WHITE,
+            health: 10.0,
+            speed: 50.0,
+            armor: 0.0,
+            gold: 10,
+            leak_damage: 1.0,
+            size: 8.0,
+            weight: 2.0,
+            min_wave: 0,
+            abilities: vec![],
+            scaling: WaveScaling {
+                gold: ScalingFormula {
+                    add_per_wave: 5.0,
+                    mul_per_wave: 0.0,
+                },
+                ..Default::default()
+            },
+            layer: EnemyLayer::Ground,
+            boss: false,
+            phases: vec![],
+        };
+        let modifiers = EnemyModifiers {
+            gold: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            EndlessCandidate::from_definition(&definition, 2, &modifiers),
+            candidate("Grunt", 30.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn mutators_change_the_whole_wave() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let base = generate_wave(&mut rng, &[candidate("Grunt", 10.0, 1.0)], 30.0);
+        let mut hasty = base.clone();
+        Mutator::Haste.apply(&mut hasty);
+        assert_eq!(hasty.groups[0].modifiers.speed, HASTE_SPEED);
+        let mut armored = base.clone();
+        Mutator::Armored.apply(&mut armored);
+        assert_eq!(armored.groups[0].modifiers.armor, ARMORED_WAVE_ARMOR);
+        let mut double = base.clone();
+        Mutator::DoublePath.apply(&mut double);
+        assert!(double.second_path);
+        assert_eq!(double.groups, base.groups);
+
+        let mutated: Vec<u32> = (0..9)
+            .filter(|i| mutator_for(&mut rng, *i).is_some())
+            .collect();
+        assert_eq!(mutated, vec![2, 5, 8]);
+    }
+
+    #[test]
+    fn generated_waves_replace_the_script() {
+        let script = WaveScript {
+            waves: vec![WaveDefinition::default()],
+        };
+        let generated = GeneratedWave {
+            wave: 3,
+            definition: generate_wave(
+                &mut StdRng::seed_from_u64(0),
+                &[candidate("Grunt", 1.0, 1.0)],
+                2.0,
+            ),
+            mutator: None,
+        };
+        let at = |wave| wave_definition(wave, Some(&script), Some(&generated)).unwrap();
+        assert_eq!(at(3).groups.len(), 1);
+        assert!(at(2).groups.is_empty());
+    }
+}
@@ -1,35 +1,46 @@
 use bevy::{
     asset::{AssetServer, Assets, Handle, LoadedFolder},
+    ecs::name::Name,
     ecs::{
         component::Component,
         entity::Entity,
         event::Event,
         observer::Trigger,
-        query::{With, Without},
+        prelude::OnAdd,
+        query::{Has, With, Without},
         resource::Resource,
         system::{Commands, Query, Res, ResMut},
     },
-    image::Image,
     log::{error, info},
-    math::{Vec2, Vec3, Vec3Swizzles, primitives::Circle},
+    math::{Vec2, Vec3Swizzles, primitives::Circle},
     prelude::{Deref, DerefMut},
     render::{mesh::Mesh, view::Visibility},
     sprite::{ColorMaterial, Sprite},
-    time::{Time, Timer},
+    time::Time,
     transform::components::Transform,
 };
-use rand::{
-    Rng, random_range, rng,
-    seq::{IndexedRandom, IteratorRandom},
-};
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
 
 use crate::{
-    assets::{ENEMY_COLOR, ENEMY_FOLDER, ENEMY_PLAYER_DAMAGE, ENEMY_RADIUS},
+    archetype::{EnemyArchetypeFolder, EnemyDefinition},
+    assets::{
+        AIR_ENEMY_Z, ARCHETYPE_FOLDER, ENEMY_COLOR, ENEMY_RADIUS, GROUND_ENEMY_Z, WAVE_SCRIPT,
+    },
+    aura::EnemyDebuff,
+    boss::{Boss, BossPhases},
+    difficulty::Difficulty,
+    economy::LedgerReason,
+    endless::{GeneratedWave, wave_definition},
     grid::{GridEntity, GridIndex, HexGridRenderRadius, PathStart},
-    path::HexPath,
+    path::{HexPath, SecondPath, spline::PathSpline},
     player::{Gold, GoldGained, Player},
-    stats::{Damage, Health, Speed, Wave},
-    tower::TowerTraversal,
+    stats::{Armor, Damage, DamageType, Health, MaxHealth, RunRng, Speed, Wave},
+    tower::{Tower, TowerTraversal},
+    veterancy::TowerRecord,
+    wave::{
+        EnemyModifiers, SpawnPoint, WaveDefinition, WaveSchedule, WaveScript, WaveScriptHandle,
+    },
 };
 
 #[derive(Event, Clone)]
@@ -42,8 +53,30 @@ pub struct EnemyMoved {
 #[derive(Event)]
 pub struct DamageTaken {
     pub amount: f32,
+    pub kind: DamageType,
+    /// Tower that dealt the damage, if any.
+    pub source: Option<Entity>,
 }
 
+/// Triggered after armor, debuffs and shields have been applied to a hit.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageDealt {
+    pub amount: f32,
+    /// Part of the hit absorbed by a `Shield`.
+    pub absorbed: f32,
+    pub kind: DamageType,
+    pub position: Vec2,
+}
+
+/// Triggered on an enemy right before it is despawned after being killed.
+#[derive(Event)]
+pub struct EnemyKilled;
+
+/// Triggered on an enemy right before it is despawned after reaching the end of
+/// the path.
+#[derive(Event)]
+pub struct EnemyLeaked;
+
 #[derive(Event)]
 pub struct EnemyRemoved {
     pub entity: Entity,
@@ -51,46 +84,92 @@ pub struct EnemyRemoved {
 
 #[derive(Component)]
 pub struct Enemy;
+/// Next path node the enemy is heading to.
 #[derive(Component)]
 pub struct EnemyCurrentTarget(pub GridIndex);
+/// Distance travelled along the `PathSpline`, or along the `FlightPath` of
+/// flying enemies.
+#[derive(Component, Deref, DerefMut, Default, Clone, Copy, Debug)]
+pub struct PathProgress(pub f32);
+/// Straight route of a flying enemy from where it spawned to the path end.
+#[derive(Component, Deref)]
+pub struct FlightPath(pub PathSpline);
+/// Marks a ground enemy following the spline of the `SecondPath`.
+#[derive(Component)]
+pub struct OnSecondPath;
+/// Where a newly spawned enemy starts.
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnLocation {
+    pub position: Vec2,
+    pub target: GridIndex,
+    pub progress: f32,
+    /// Whether the enemy follows the `SecondPath`.
+    pub second_path: bool,
+}
+
+/// Route an enemy follows: its `FlightPath`, the `SecondPath` it spawned on,
+/// or else the main `PathSpline`.
+pub fn enemy_route<'a>(
+    spline: &'a PathSpline,
+    second: Option<&'a SecondPath>,
+    flight: Option<&'a FlightPath>,
+    on_second_path: bool,
+) -> &'a PathSpline {
+    match (flight, second) {
+        (Some(flight), _) => &flight.0,
+        (None, Some(second)) if on_second_path => &second.spline,
+        _ => spline,
+    }
+}
 #[derive(Component, Deref)]
 pub struct EnemySize(pub f32);
 #[derive(Resource)]
 pub struct EnemyMesh(pub Handle<Mesh>);
 #[derive(Resource)]
 pub struct EnemyMaterial(pub Handle<ColorMaterial>);
-#[derive(Resource, Deref, DerefMut)]
-pub struct SpawnTimer(pub Timer);
-#[derive(Resource, Deref)]
-pub struct EnemyImageFolder(Handle<LoadedFolder>);
+#[derive(Component, Deref)]
+pub struct EnemyArchetype(pub Handle<EnemyDefinition>);
+/// Ground enemies follow the `HexPath`, air enemies fly straight to the path end
+/// and can only be targeted by anti-air towers.
+#[derive(Component, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnemyLayer {
+    #[default]
+    Ground,
+    Air,
+}
 
-impl EnemyImageFolder {
-    pub fn get_random_enemy_image<R: Rng>(
-        &self,
-        rng: &mut R,
-        assets: &Assets<LoadedFolder>,
-    ) -> Handle<Image> {
-        let real = assets.get(&self.0).unwrap();
-        real.handles.choose(rng).unwrap().clone().typed::<Image>()
+impl EnemyLayer {
+    pub fn z(&self) -> f32 {
+        match self {
+            EnemyLayer::Ground => GROUND_ENEMY_Z,
+            EnemyLayer::Air => AIR_ENEMY_Z,
+        }
     }
 }
-#[derive(Resource)]
-pub struct SpawnCounter {
-    pub current: u32,
-    pub max: u32,
-}
+/// Absorbs incoming damage until depleted.
+#[derive(Component, Deref, DerefMut)]
+pub struct Shield(pub f32);
 
-impl SpawnCounter {
-    pub fn can_spawn(&self) -> bool {
-        self.current < self.max
-    }
-    pub fn new(max: u32) -> Self {
-        Self { current: 0, max }
+impl Shield {
+    /// Absorbs as much of `amount` as possible and returns the damage left over.
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let absorbed = self.0.min(amount);
+        self.0 -= absorbed;
+        amount - absorbed
     }
 }
-
-pub fn enemies_are_loaded(enemies: Res<EnemyImageFolder>, asset_server: Res<AssetServer>) -> bool {
-    asset_server.is_loaded_with_dependencies(enemies.id())
+/// Heals the enemy by the given amount per second, up to its `MaxHealth`.
+#[derive(Component, Deref)]
+pub struct Regeneration(pub f32);
+/// Whether the archetype definitions and the wave script are loaded. Their
+/// sprites are only needed for rendering, so they are not waited for.
+pub fn enemies_are_loaded(
+    enemies: Res<EnemyArchetypeFolder>,
+    script: Res<WaveScriptHandle>,
+    asset_server: Res<AssetServer>,
+) -> bool {
+    asset_server.is_loaded_with_direct_dependencies(enemies.id())
+        && asset_server.is_loaded_with_direct_dependencies(script.id())
 }
 pub fn setup_enemy_resources(
     mut commands: Commands,
@@ -103,96 +182,192 @@ pub fn setup_enemy_resources(
     let mat = materials.add(ENEMY_COLOR);
     commands.insert_resource(EnemyMaterial(mat));
 
-    let enemies = asset_server.load_folder(ENEMY_FOLDER);
-    commands.insert_resource(EnemyImageFolder(enemies));
+    let archetypes = asset_server.load_folder(ARCHETYPE_FOLDER);
+    commands.insert_resource(EnemyArchetypeFolder(archetypes));
+    commands.insert_resource(WaveScriptHandle(asset_server.load(WAVE_SCRIPT)));
 }
 
-pub fn init_spawn_timer(mut commands: Commands, wave: Res<Wave>) {
-    commands.insert_resource(SpawnTimer(Timer::from_seconds(
-        1.0,
-        bevy::time::TimerMode::Repeating,
-    )));
-
-    commands.insert_resource(SpawnCounter::new(20 + wave.0 * 5));
+pub fn init_spawn_timer(
+    mut commands: Commands,
+    wave: Res<Wave>,
+    script: Res<WaveScriptHandle>,
+    scripts: Res<Assets<WaveScript>>,
+    difficulty: Res<Difficulty>,
+    generated: Option<Res<GeneratedWave>>,
+) {
+    let definition = wave_definition(wave.0, scripts.get(&script.0), generated.as_deref())
+        .unwrap_or_else(|| {
+            error!("no wave script entry for wave {}", wave.0);
+            WaveDefinition::default()
+        });
+    commands.insert_resource(WaveSchedule::new(
+        &difficulty.settings().scale_wave(&definition),
+    ));
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn spawn_enemy(
     mut commands: Commands,
     wave: Res<Wave>,
-    //mesh: Res<EnemyMesh>,
-    //material: Res<EnemyMaterial>,
     starts: Query<&GridEntity, With<PathStart>>,
     size: Res<HexGridRenderRadius>,
     time: Res<Time>,
-    mut spawn_timer: ResMut<SpawnTimer>,
-    mut spawn_counter: ResMut<SpawnCounter>,
-    hex_path: Res<HexPath<GridIndex>>,
-    enemy_image_folder: Res<EnemyImageFolder>,
-    loaded_folder_assets: Res<Assets<LoadedFolder>>,
+    mut schedule: ResMut<WaveSchedule>,
+    spline: Res<PathSpline>,
+    second: Option<Res<SecondPath>>,
+    difficulty: Res<Difficulty>,
+    archetypes: Res<EnemyArchetypeFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<EnemyDefinition>>,
+    mut rng: ResMut<RunRng>,
 ) {
-    let mut rng = rng();
-    let start = starts.iter().choose(&mut rng);
-    if start.is_none() {
+    let mut starts: Vec<GridIndex> = starts.iter().map(|s| s.0).collect();
+    if starts.is_empty() {
         error!("Failed to get start");
         return;
     }
-    spawn_timer.tick(time.delta());
-    if spawn_timer.just_finished() && spawn_counter.can_spawn() {
-        let world_pos = start.unwrap().0.to_world_pos(**size);
-
-        if let Some(n) = hex_path.get_next(start.unwrap().0) {
-            let health = random_range(10.0..=30.0) + wave.0 as f32 * 10.0;
-            let speed = random_range(30.0..100.0) + wave.0 as f32 * 30.0;
-            let gold = random_range(5..=10) * (wave.0 + 1);
-            let image = enemy_image_folder.get_random_enemy_image(&mut rng, &loaded_folder_assets);
-            info!("image: {image:?}");
-            commands
-                .spawn((
-                    Enemy,
-                    EnemyCurrentTarget(n),
-                    Damage(ENEMY_PLAYER_DAMAGE),
-                    Health(health),
-                    Speed(speed),
-                    Gold(gold),
-                    EnemySize(ENEMY_RADIUS),
-                    //Mesh2d(mesh.0.clone()),
-                    //MeshMaterial2d(material.0.clone()),
-                    Visibility::Visible,
-                    Transform::from_xyz(world_pos.x, world_pos.y, 10.0),
-                    Sprite {
-                        image,
-                        custom_size: Some(Vec2::new(ENEMY_RADIUS * 2.0, ENEMY_RADIUS * 2.0)),
-                        color: bevy::color::Color::hsl(
-                            random_range(0.0..=360.0),
-                            random_range(0.0..=1.0),
-                            random_range(0.0..=1.0),
-                        ),
-                        ..Default::default()
-                    },
-                ))
-                .observe(on_hit);
-            spawn_counter.current += 1;
-        } else {
-            info!("Enemy: at {}, index {:?}", world_pos, start.unwrap().0);
+    starts.sort_by_key(|s| (s.q, s.r));
+    for group in schedule.tick(time.delta_secs()) {
+        let start = match group.spawn {
+            SpawnPoint::Any => *starts.choose(&mut rng.0).unwrap(),
+            SpawnPoint::Index(i) => starts[i % starts.len()],
+        };
+        let world_pos = start.to_world_pos(**size);
+        let second_path = second.as_ref().is_some_and(|s| s.path.start == start);
+        let route = enemy_route(&spline, second.as_deref(), None, second_path);
+        let Some(n) = route.next_node(0.0) else {
+            info!("Enemy: at {}, index {:?}", world_pos, start);
             error!("could not get next destination");
-        }
+            continue;
+        };
+        let Some(handle) = archetypes.find(&group.enemy, &folders, &definitions) else {
+            error!("unknown enemy archetype {}", group.enemy);
+            continue;
+        };
+        let definition = definitions.get(&handle).unwrap();
+        info!("spawning {}", definition.name);
+        spawn_archetype(
+            &mut commands,
+            handle,
+            definition,
+            wave.0,
+            &difficulty
+                .settings()
+                .enemy_modifiers(&group.modifiers, wave.0),
+            SpawnLocation {
+                position: world_pos,
+                target: n,
+                progress: 0.0,
+                second_path,
+            },
+        );
+    }
+}
+
+/// Spawns an enemy of the given archetype at `location`.
+pub fn spawn_archetype(
+    commands: &mut Commands,
+    handle: Handle<EnemyDefinition>,
+    definition: &EnemyDefinition,
+    wave: u32,
+    modifiers: &EnemyModifiers,
+    location: SpawnLocation,
+) -> Entity {
+    let position = location.position;
+    let stats = definition.stats_for_wave(wave).with_modifiers(modifiers);
+    let mut enemy = commands.spawn((
+        Enemy,
+        Name::new(definition.name.clone()),
+        EnemyArchetype(handle),
+        EnemyCurrentTarget(location.target),
+        PathProgress(location.progress),
+        (
+            Damage(definition.leak_damage),
+            Health(stats.health),
+            MaxHealth(stats.health),
+            Speed(stats.speed),
+            Armor(definition.armor + modifiers.armor),
+            Gold(stats.gold),
+        ),
+        EnemySize(definition.size),
+        definition.layer,
+        EnemyDebuff::default(),
+        Visibility::Visible,
+        Transform::from_xyz(position.x, position.y, definition.layer.z()),
+        Sprite {
+            image: definition.sprite.clone(),
+            custom_size: Some(Vec2::splat(definition.size * 2.0)),
+            color: definition.tint,
+            ..Default::default()
+        },
+    ));
+    for ability in &definition.abilities {
+        ability.insert_into(&mut enemy);
+    }
+    if location.second_path {
+        enemy.insert(OnSecondPath);
+    }
+    if definition.boss {
+        enemy.insert((Boss, BossPhases::new(&definition.phases)));
     }
+    enemy.observe(on_hit);
+    enemy.id()
 }
 
+#[allow(clippy::type_complexity)]
 pub fn on_hit(
     trigger: Trigger<DamageTaken>,
     mut commands: Commands,
-    mut query: Query<(&mut Health, &Gold), With<Enemy>>,
+    mut query: Query<
+        (
+            &mut Health,
+            &Gold,
+            &Armor,
+            &EnemyDebuff,
+            &Transform,
+            Option<&mut Shield>,
+        ),
+        With<Enemy>,
+    >,
+    mut towers: Query<&mut TowerRecord, With<Tower>>,
 ) {
-    let Ok((mut h, g)) = query.get_mut(trigger.target()) else {
+    let Ok((mut h, g, armor, debuff, transform, shield)) = query.get_mut(trigger.target()) else {
         return;
     };
+    if h.0 <= 0.0 {
+        return;
+    }
 
-    h.0 -= trigger.event().amount;
+    let mut amount = armor.reduce(trigger.event().amount) * debuff.damage_factor();
+    let mut absorbed = 0.0;
+    if let Some(mut shield) = shield {
+        let remaining = shield.absorb(amount);
+        absorbed = amount - remaining;
+        amount = remaining;
+        if shield.0 <= 0.0 {
+            commands.entity(trigger.target()).remove::<Shield>();
+        }
+    }
+    let dealt = amount.min(h.0);
+    commands.trigger(DamageDealt {
+        amount: dealt,
+        absorbed,
+        kind: trigger.event().kind,
+        position: transform.translation.xy(),
+    });
+    h.0 -= amount;
+    if let Some(source) = trigger.event().source
+        && let Ok(mut record) = towers.get_mut(source)
+    {
+        record.credit(dealt, h.0 <= 0.0);
+    }
     if h.0 <= 0.0 {
+        commands.trigger_targets(EnemyKilled, trigger.target());
         commands.entity(trigger.target()).despawn();
-        commands.trigger(GoldGained { amount: g.0 });
+        commands.trigger(GoldGained {
+            amount: g.0,
+            reason: LedgerReason::Kill,
+        });
     }
 }
 
@@ -204,42 +379,41 @@ pub fn update_enemy(
             Entity,
             &mut Transform,
             &mut EnemyCurrentTarget,
+            &mut PathProgress,
             &Damage,
             &Speed,
             &Health,
+            &EnemyDebuff,
+            Option<&FlightPath>,
+            Has<OnSecondPath>,
         ),
         (With<Enemy>, Without<Player>),
     >,
-    path: Res<HexPath<GridIndex>>,
-    size: Res<HexGridRenderRadius>,
+    spline: Res<PathSpline>,
+    second: Option<Res<SecondPath>>,
     time: Res<Time>,
     mut player: Query<&mut Health, With<Player>>,
 ) {
     if let Ok(mut p_h) = player.single_mut() {
-        for (e, mut t, mut target, d, s, h) in enemies {
+        for (e, mut t, mut target, mut progress, d, s, h, debuff, flight, on_second_path) in enemies
+        {
             if h.0 < 0.0 {
                 commands.entity(e).despawn();
                 continue;
             }
-            let wp = t.translation.xy();
-            if (wp.distance(target.0.to_world_pos(**size))) < ENEMY_RADIUS {
-                if path.end == target.0 {
-                    commands.entity(e).despawn();
-                    p_h.0 -= d.0;
-                    continue;
-                }
-                if let Some(n) = path.get_next(target.0) {
-                    target.0 = n
-                } else {
-                    error!("failed to get next pos");
-                    continue;
-                }
+            let route = enemy_route(&spline, second.as_deref(), flight, on_second_path);
+            progress.0 += s.0 * debuff.speed_factor() * time.delta_secs();
+            if route.is_finished(progress.0) {
+                commands.trigger_targets(EnemyLeaked, e);
+                commands.entity(e).despawn();
+                p_h.0 -= d.0;
+                continue;
+            }
+            let position = route.position_at(progress.0);
+            t.translation = position.extend(t.translation.z);
+            if let Some(n) = route.next_node(progress.0) {
+                target.0 = n;
             }
-
-            let target_pos = target.0.to_world_pos(**size);
-            let dir = (target_pos - t.translation.xy()).normalize();
-
-            t.translation += Vec3::new(dir.x, dir.y, 0.0) * s.0 * time.delta_secs();
             commands.trigger(EnemyMoved {
                 entity: e,
                 position: t.translation.xy(),
@@ -254,3 +428,37 @@ pub fn update_enemy(
         }
     }
 }
+
+/// Sends flying enemies straight from where they spawn to the path end.
+pub fn plan_flight_path(
+    trigger: Trigger<OnAdd, Enemy>,
+    mut commands: Commands,
+    enemies: Query<(&Transform, &EnemyLayer)>,
+    path: Option<Res<HexPath<GridIndex>>>,
+    size: Res<HexGridRenderRadius>,
+) {
+    let (Ok((transform, layer)), Some(path)) = (enemies.get(trigger.target()), path) else {
+        return;
+    };
+    if *layer == EnemyLayer::Air {
+        commands.entity(trigger.target()).insert((
+            FlightPath(PathSpline::straight(
+                transform.translation.xy(),
+                path.end,
+                **size,
+            )),
+            PathProgress(0.0),
+        ));
+    }
+}
+
+pub fn regenerate(
+    enemies: Query<(&mut Health, &MaxHealth, &Regeneration), With<Enemy>>,
+    time: Res<Time>,
+) {
+    for (mut h, max, regeneration) in enemies {
+        if h.0 > 0.0 && h.0 < max.0 {
+            h.0 = (h.0 + regeneration.0 * time.delta_secs()).min(max.0);
+        }
+    }
+}
@@ -6,7 +6,6 @@ use std::{
 use bevy::{
     app::{Plugin, Startup},
     asset::{Assets, Handle},
-    color::Color,
     ecs::{
         entity::Entity,
         hierarchy::ChildOf,
@@ -23,20 +22,19 @@ use bevy::{
     platform::collections::{HashMap, HashSet},
     prelude::*,
     render::mesh::{Mesh, Mesh2d},
-    sprite::{ColorMaterial, ColorMaterialUniform, MeshMaterial2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
     transform::components::Transform,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    assets::{
-        DEFAULT_HEX_COLOR, HOVER_TINT_COLOR, PATH_COLOR, PATH_DEBUG_COLOR, PATH_END_COLOR,
-        PATH_START_COLOR, TOWER_COST,
-    },
+    assets::{DEFAULT_HEX_COLOR, HOVER_TINT_COLOR, PATH_COLOR, PATH_END_COLOR, PATH_START_COLOR},
+    command::PlayerCommand,
     def_enum,
     enemy::{Enemy, EnemyMoved},
-    path::HexPath,
-    player::{Gold, Player},
-    tower::{BaseTowerImage, spawn_tower_at},
+    path::{HexPath, regeneration::FreeRelocation},
+    settings::Settings,
+    tower::{SelectedTower, SelectedTowerKind, Tower, TowerIndex},
 };
 #[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GridSet;
@@ -63,6 +61,8 @@ impl Plugin for GridPlugin {
             ((self.column_width) / 2.0) - 2.0 * self.padding,
         ));
         app.insert_resource(HexSpatialGrid::default());
+        app.init_resource::<MapSize>();
+        app.add_systems(Update, resize_grid.run_if(resource_changed::<MapSize>));
         app.add_systems(
             Startup,
             (prepare_colors_materials, init_grid)
@@ -182,6 +182,35 @@ impl From<(GridEntry, GridEntryState)> for HexGridEntryState {
 //    }
 //}
 
+/// Board sizes offered when starting a new game.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MapSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl MapSize {
+    pub const ALL: [MapSize; 3] = [MapSize::Small, MapSize::Medium, MapSize::Large];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapSize::Small => "Small",
+            MapSize::Medium => "Medium",
+            MapSize::Large => "Large",
+        }
+    }
+
+    pub fn columns_and_rows(&self) -> (i32, i32) {
+        match self {
+            MapSize::Small => (11, 7),
+            MapSize::Medium => (15, 10),
+            MapSize::Large => (19, 12),
+        }
+    }
+}
+
 #[derive(Resource, Deref, DerefMut)]
 pub struct DefaultHexMaterial(pub Handle<ColorMaterial>);
 #[derive(Resource, Deref, DerefMut)]
@@ -250,6 +279,34 @@ pub fn init_grid(
     commands.insert_resource(grid);
 }
 
+/// Rebuilds the hex grid when the `MapSize` no longer matches it.
+#[allow(clippy::too_many_arguments)]
+pub fn resize_grid(
+    mut commands: Commands,
+    map: Res<MapSize>,
+    mut columns: ResMut<HexGridColumns>,
+    mut rows: ResMut<HexGridRows>,
+    mut width: ResMut<HexGridWidth>,
+    mut height: ResMut<HexGridHeight>,
+    column_width: Res<HexGridColumnWidth>,
+    entries: Query<Entity, With<GridEntity>>,
+) {
+    let (new_columns, new_rows) = map.columns_and_rows();
+    if (new_columns, new_rows) == (**columns, **rows) {
+        return;
+    }
+    info!("resizing grid to {new_columns}x{new_rows}");
+    height.0 = height.0 / rows.0 as f32 * new_rows as f32;
+    width.0 = column_width.0 * new_columns as f32;
+    columns.0 = new_columns;
+    rows.0 = new_rows;
+    for entry in entries {
+        commands.entity(entry).despawn();
+    }
+    commands.remove_resource::<HexPath<GridIndex>>();
+    commands.run_system_cached(init_grid);
+}
+
 #[derive(Component)]
 pub struct Hover;
 
@@ -283,26 +340,48 @@ pub fn on_hex_out(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn on_hex_click(
     mut trigger: Trigger<Pointer<Click>>,
-    commands: Commands,
-    base_tower_image: Res<BaseTowerImage>,
-    mut hex_grid: ResMut<HexHashGrid>,
-    mut player_gold: Single<&mut Gold, With<Player>>,
+    mut commands: Commands,
+    hex_grid: Res<HexHashGrid>,
     grid_query: Query<&GridEntity>,
+    towers: Query<(&TowerIndex, Has<FreeRelocation>), With<Tower>>,
+    selected: Res<SelectedTowerKind>,
+    selected_tower: Res<SelectedTower>,
+    time: Res<Time<Virtual>>,
+    settings: Res<Settings>,
 ) {
-    if let Ok(index) = grid_query.get(trigger.target)
-        && hex_grid[index.0] == GridEntry::None
-        && spawn_tower_at(
-            trigger.target,
-            commands,
-            base_tower_image,
-            &Gold(TOWER_COST),
-            &mut player_gold,
-        )
-    {
-        info!("set tower: {:?}", index.0);
-        hex_grid[index.0] = GridEntry::Tower;
+    let Ok(index) = grid_query.get(trigger.target) else {
+        trigger.propagate(true);
+        return;
+    };
+    if time.is_paused() && !settings.build_while_paused {
+        trigger.propagate(true);
+        return;
+    }
+    let relocating = selected_tower
+        .0
+        .and_then(|t| towers.get(t).ok())
+        .filter(|(_, free)| *free);
+    let command = match trigger.button {
+        PointerButton::Primary if hex_grid[index.0] == GridEntry::None => match relocating {
+            Some((from, _)) => Some(PlayerCommand::MoveTower {
+                from: from.0,
+                to: index.0,
+            }),
+            None => Some(PlayerCommand::PlaceTower {
+                index: index.0,
+                kind: **selected,
+            }),
+        },
+        PointerButton::Secondary if hex_grid[index.0] == GridEntry::Tower => {
+            Some(PlayerCommand::SellTower { index: index.0 })
+        }
+        _ => None,
+    };
+    if let Some(command) = command {
+        commands.trigger(command);
     }
     trigger.propagate(true);
 }
@@ -429,6 +508,15 @@ impl HexSpatialGrid {
         initial_set.into_iter()
     }
 
+    pub fn get_within(&self, index: &GridIndex, radius: i32) -> impl Iterator<Item = Entity> {
+        index
+            .within(radius)
+            .filter_map(|i| self.data.get(&i))
+            .flat_map(|s| s.iter().cloned())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     pub fn remove(&mut self, entity: Entity) {
         let Some(position) = self.entries.remove(&entity) else {
             return;
@@ -499,6 +587,11 @@ impl HexHashGrid {
         });
     }
 
+    /// Marks every tile as empty, removing towers and the path.
+    pub fn clear(&mut self) {
+        self.data.values_mut().for_each(|e| *e = GridEntry::None);
+    }
+
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut GridEntry> {
         self.data.values_mut()
     }
@@ -553,7 +646,7 @@ pub struct HexGridHeight(f32);
 #[derive(Resource, DerefMut, Deref, Clone, Copy)]
 pub struct HexGridCirumRadius(f32);
 #[derive(Resource, DerefMut, Deref, Clone, Copy)]
-pub struct HexGridRenderRadius(f32);
+pub struct HexGridRenderRadius(pub f32);
 
 pub static AXIAL_CONVERT: LazyLock<Mat2> =
     LazyLock::new(|| Mat2::from_cols(Vec2::new(sqrt(3.0), 0.0), Vec2::new(sqrt(3.0) / 2.0, 1.5)));
@@ -564,7 +657,7 @@ pub static AXIAL_INVERTED: LazyLock<Mat2> = LazyLock::new(|| {
     )
 });
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub struct GridIndex {
     pub q: i32,
     pub r: i32,
@@ -637,6 +730,35 @@ impl GridIndex {
 
         GridIndex::from_axial_vec(q)
     }
+
+    pub fn distance(&self, other: &GridIndex) -> i32 {
+        let d = *self - *other;
+        (d.q.abs() + d.r.abs() + (d.q + d.r).abs()) / 2
+    }
+
+    pub fn neighbours(&self) -> impl Iterator<Item = GridIndex> {
+        let center = *self;
+        GridDirections::VARIANTS
+            .iter()
+            .map(move |d| center + d.get())
+    }
+
+    /// All indices exactly `radius` steps away from `self`.
+    pub fn ring(&self, radius: i32) -> impl Iterator<Item = GridIndex> {
+        let center = *self;
+        self.within(radius)
+            .filter(move |i| i.distance(&center) == radius)
+    }
+
+    /// All indices at most `radius` steps away from `self`, including `self`.
+    pub fn within(&self, radius: i32) -> impl Iterator<Item = GridIndex> {
+        let center = *self;
+        (-radius..=radius).flat_map(move |dq| {
+            let start = (-radius).max(-dq - radius);
+            let end = radius.min(-dq + radius);
+            (start..=end).map(move |dr| center + GridIndex::new(dq, dr))
+        })
+    }
 }
 
 impl IndexMut<GridIndex> for HexHashGrid {
@@ -695,3 +817,34 @@ def_enum! {
         BOTTOMRIGHT => GridIndex::new(1, -1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GridIndex;
+
+    #[test]
+    fn ring_sizes() {
+        let center = GridIndex::new(2, -3);
+        assert_eq!(center.ring(0).count(), 1);
+        assert_eq!(center.ring(1).count(), 6);
+        assert_eq!(center.ring(2).count(), 12);
+        assert_eq!(center.ring(3).count(), 18);
+    }
+
+    #[test]
+    fn ring_one_is_neighbours() {
+        let center = GridIndex::new(-1, 4);
+        let mut ring: Vec<GridIndex> = center.ring(1).collect();
+        let mut neighbours: Vec<GridIndex> = center.neighbours().collect();
+        ring.sort_by_key(|i| (i.q, i.r));
+        neighbours.sort_by_key(|i| (i.q, i.r));
+        assert_eq!(ring, neighbours);
+    }
+
+    #[test]
+    fn within_counts_all_rings() {
+        let center = GridIndex::new(0, 0);
+        assert_eq!(center.within(2).count(), 1 + 6 + 12);
+        assert!(center.within(2).all(|i| i.distance(&center) <= 2));
+    }
+}
@@ -0,0 +1,342 @@
+use std::path::Path;
+
+use bevy::{
+    prelude::*, render::texture::ImagePlugin, state::app::StatesPlugin, time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DuringWave, GameState, SimulationPlugin,
+    build_phase::start_build_phase,
+    command::{PlayerCommand, apply_player_command},
+    difficulty::{Difficulty, StartRun},
+    economy::Ledger,
+    generate_path,
+    grid::{GridIndex, MapSize, resize_grid},
+    player::{Gold, Player},
+    replay::TickTransitionsPlugin,
+    results::RunStats,
+    ron_file::RonFile,
+    stats::{Health, RunSeed, Wave},
+    tower::{Tower, TowerIndex, TowerKind},
+    veterancy::TowerRecord,
+};
+
+/// Simulates the run of a `BalanceScript` without window, audio or UI, one
+/// fixed tick per frame as fast as possible, and prints a `BalanceReport` as
+/// JSON.
+pub fn run(script: impl AsRef<Path>) -> AppExit {
+    let script = match BalanceScript::load(script) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("{e}");
+            return AppExit::error();
+        }
+    };
+    let mut app = simulation_app();
+    app.add_plugins(HeadlessPlugin);
+    app.insert_resource(script);
+    app.run()
+}
+
+/// An app running the `SimulationPlugin` without window, GPU or audio device,
+/// advancing one fixed tick per update.
+pub fn simulation_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins((
+        AssetPlugin::default(),
+        ImagePlugin::default(),
+        TransformPlugin,
+        StatesPlugin,
+    ));
+    app.init_asset::<Mesh>();
+    app.init_asset::<ColorMaterial>();
+    app.add_plugins(SimulationPlugin);
+    let timestep = app.world().resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    app
+}
+
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TickTransitionsPlugin);
+        app.init_resource::<CombatClock>();
+        app.init_resource::<SoldTowers>();
+        app.add_observer(stamp_built_tower);
+        app.add_observer(record_sold_tower);
+        app.add_systems(OnEnter(GameState::MainMenu), start_scripted_run);
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            (
+                write_report.run_if(scripted_waves_done),
+                apply_scripted_builds.run_if(not(scripted_waves_done)),
+            )
+                .after(generate_path)
+                .after(start_build_phase),
+        );
+        for state in [GameState::GameOver, GameState::Victory] {
+            app.add_systems(OnEnter(state), write_report);
+        }
+        app.add_systems(FixedUpdate, tick_combat_clock.in_set(DuringWave));
+    }
+}
+
+/// How a headless run is set up and what the player does during it.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BalanceScript {
+    pub seed: u64,
+    pub map: MapSize,
+    pub difficulty: Difficulty,
+    /// Waves to simulate before reporting, unless the run ends earlier.
+    pub waves: u32,
+    pub builds: Vec<ScriptedCommand>,
+}
+
+/// A command given as soon as the build phase before `wave` starts. The first
+/// wave is 0.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptedCommand {
+    pub wave: u32,
+    pub command: PlayerCommand,
+}
+
+impl RonFile for BalanceScript {
+    const NAME: &'static str = "balance script";
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Victory,
+    GameOver,
+    /// The scripted number of waves was reached first.
+    WaveLimit,
+}
+
+/// Outcome of a headless run.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BalanceReport {
+    pub seed: u64,
+    pub outcome: Outcome,
+    pub waves_survived: u32,
+    pub leaks: u32,
+    pub kills: u32,
+    pub gold_earned: u32,
+    pub gold_spent: u32,
+    pub gold_left: u32,
+    pub health_left: f32,
+    pub towers: Vec<TowerReport>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TowerReport {
+    pub index: GridIndex,
+    pub kind: TowerKind,
+    pub built_before_wave: u32,
+    pub sold: bool,
+    pub damage_dealt: f32,
+    pub kills: u32,
+    /// Damage per second of combat since the tower was built.
+    pub dps: f32,
+}
+
+impl TowerReport {
+    pub fn new(
+        index: GridIndex,
+        kind: TowerKind,
+        record: &TowerRecord,
+        built: &BuiltAt,
+        combat_seconds: f32,
+    ) -> Self {
+        let seconds = combat_seconds - built.combat_seconds;
+        Self {
+            index,
+            kind,
+            built_before_wave: built.wave,
+            sold: false,
+            damage_dealt: record.damage_dealt,
+            kills: record.kills,
+            dps: if seconds > 0.0 {
+                record.damage_dealt / seconds
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Seconds spent in waves, the time towers have to deal damage.
+#[derive(Resource, Deref, DerefMut, Default, Debug)]
+pub struct CombatClock(pub f32);
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BuiltAt {
+    pub wave: u32,
+    pub combat_seconds: f32,
+}
+
+/// Reports of the towers sold during the run.
+#[derive(Resource, Deref, DerefMut, Default, Debug)]
+pub struct SoldTowers(pub Vec<TowerReport>);
+
+pub fn scripted_waves_done(script: Res<BalanceScript>, wave: Res<Wave>) -> bool {
+    wave.0 >= script.waves
+}
+
+pub fn start_scripted_run(
+    mut commands: Commands,
+    script: Res<BalanceScript>,
+    mut seed: ResMut<RunSeed>,
+    mut map: ResMut<MapSize>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    *seed = RunSeed(script.seed);
+    *map = script.map;
+    *difficulty = script.difficulty;
+    commands.run_system_cached(resize_grid);
+    commands.trigger(StartRun);
+}
+
+pub fn apply_scripted_builds(mut commands: Commands, script: Res<BalanceScript>, wave: Res<Wave>) {
+    for build in script.builds.iter().filter(|b| b.wave == wave.0) {
+        commands.run_system_cached_with(apply_player_command, build.command);
+    }
+}
+
+pub fn tick_combat_clock(mut clock: ResMut<CombatClock>, time: Res<Time>) {
+    clock.0 += time.delta_secs();
+}
+
+pub fn stamp_built_tower(
+    trigger: Trigger<OnAdd, Tower>,
+    mut commands: Commands,
+    wave: Res<Wave>,
+    clock: Res<CombatClock>,
+) {
+    commands.entity(trigger.target()).insert(BuiltAt {
+        wave: wave.0,
+        combat_seconds: clock.0,
+    });
+}
+
+pub fn record_sold_tower(
+    trigger: Trigger<OnRemove, Tower>,
+    towers: Query<(&TowerIndex, &TowerKind, Option<&TowerRecord>, &BuiltAt)>,
+    clock: Res<CombatClock>,
+    mut sold: ResMut<SoldTowers>,
+) {
+    if let Ok((index, kind, record, built)) = towers.get(trigger.target()) {
+        let record = record.copied().unwrap_or_default();
+        sold.push(TowerReport {
+            sold: true,
+            ..TowerReport::new(index.0, *kind, &record, built, clock.0)
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn write_report(
+    mut exit: EventWriter<AppExit>,
+    script: Res<BalanceScript>,
+    state: Res<State<GameState>>,
+    stats: Res<RunStats>,
+    ledger: Res<Ledger>,
+    player: Single<(&Gold, &Health), With<Player>>,
+    towers: Query<(&TowerIndex, &TowerKind, Option<&TowerRecord>, &BuiltAt)>,
+    clock: Res<CombatClock>,
+    sold: Res<SoldTowers>,
+) {
+    let (gold, health) = *player;
+    let mut reports: Vec<TowerReport> = sold.0.clone();
+    reports.extend(towers.iter().map(|(index, kind, record, built)| {
+        let record = record.copied().unwrap_or_default();
+        TowerReport::new(index.0, *kind, &record, built, clock.0)
+    }));
+    reports.sort_by_key(|t| (t.built_before_wave, t.index.q, t.index.r));
+    let report = BalanceReport {
+        seed: script.seed,
+        outcome: match state.get() {
+            GameState::Victory => Outcome::Victory,
+            GameState::GameOver => Outcome::GameOver,
+            _ => Outcome::WaveLimit,
+        },
+        waves_survived: stats.waves_survived,
+        leaks: stats.enemies_leaked,
+        kills: stats.enemies_killed,
+        gold_earned: stats.gold_earned,
+        gold_spent: ledger.expenses,
+        gold_left: gold.0,
+        health_left: health.0,
+        towers: reports,
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => {
+            println!("{json}");
+            exit.write(AppExit::Success);
+        }
+        Err(e) => {
+            eprintln!("could not write report: {e}");
+            exit.write(AppExit::error());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::HexHashGrid;
+
+    #[test]
+    fn example_script_parses() {
+        let script: BalanceScript =
+            ron::de::from_str(include_str!("../balance/example.ron")).unwrap();
+        assert!(script.waves > 0);
+        assert!(
+            script
+                .builds
+                .iter()
+                .any(|b| matches!(b.command, PlayerCommand::PlaceTower { .. }))
+        );
+    }
+
+    #[test]
+    fn dps_counts_combat_time_since_built() {
+        let record = TowerRecord {
+            damage_dealt: 300.0,
+            kills: 2,
+            ..Default::default()
+        };
+        let index = GridIndex { q: 1, r: 2 };
+        let built = BuiltAt {
+            wave: 1,
+            combat_seconds: 20.0,
+        };
+        let report = TowerReport::new(index, TowerKind::Frost, &record, &built, 50.0);
+        assert_eq!(report.dps, 10.0);
+        assert_eq!(report.kills, 2);
+        let unused = TowerReport::new(index, TowerKind::Frost, &record, &built, 20.0);
+        assert_eq!(unused.dps, 0.0);
+    }
+
+    #[test]
+    fn simulation_loads_without_a_gpu() {
+        let mut app = simulation_app();
+        app.finish();
+        app.cleanup();
+        let started = std::time::Instant::now();
+        while *app.world().resource::<State<GameState>>().get() != GameState::MainMenu {
+            assert!(
+                started.elapsed().as_secs() < 30,
+                "assets did not finish loading"
+            );
+            app.update();
+        }
+        let world = app.world_mut();
+        assert_eq!(world.query::<&Player>().iter(world).count(), 1);
+        assert!(world.resource::<HexHashGrid>().keys().next().is_some());
+    }
+}
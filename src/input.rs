@@ -3,8 +3,10 @@ use bevy::{
     ecs::{
         resource::Resource,
         schedule::{IntoScheduleConfigs, SystemSet},
-        system::{Commands, Query},
+        system::{Commands, Query, Res, ResMut},
     },
+    input::{ButtonInput, keyboard::KeyCode},
+    log::info,
     math::Vec2,
     prelude::{Deref, DerefMut},
     render::camera::Camera,
@@ -12,11 +14,21 @@ use bevy::{
     window::Window,
 };
 
+use crate::{
+    settings::Settings,
+    tower::{SelectedTower, SelectedTowerKind, TowerKind},
+};
+
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        app.add_systems(Update, update_world_pos.in_set(InputSet));
+        app.add_systems(
+            Update,
+            (update_world_pos, select_tower_kind, toggle_settings).in_set(InputSet),
+        );
         app.insert_resource(MouseWorldPos(None));
+        app.init_resource::<SelectedTowerKind>();
+        app.init_resource::<SelectedTower>();
     }
 }
 
@@ -37,3 +49,38 @@ pub fn update_world_pos(
         .and_then(|cursor| c.viewport_to_world_2d(t, cursor).ok());
     commands.insert_resource(MouseWorldPos(pos));
 }
+
+static TOWER_HOTKEYS: [KeyCode; 5] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+];
+
+pub fn select_tower_kind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedTowerKind>,
+    mut selected_tower: ResMut<SelectedTower>,
+) {
+    if keys.just_pressed(KeyCode::Escape) && selected_tower.is_some() {
+        selected_tower.0 = None;
+    }
+    for (key, kind) in TOWER_HOTKEYS.iter().zip(TowerKind::ALL) {
+        if keys.just_pressed(*key) && selected.0 != kind {
+            info!("selected tower: {}", kind.name());
+            selected.0 = kind;
+        }
+    }
+}
+
+pub fn toggle_settings(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::KeyH) {
+        settings.health_bars = !settings.health_bars;
+        info!("health bars: {}", settings.health_bars);
+    }
+    if keys.just_pressed(KeyCode::KeyN) {
+        settings.damage_numbers = !settings.damage_numbers;
+        info!("damage numbers: {}", settings.damage_numbers);
+    }
+}
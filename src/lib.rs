@@ -0,0 +1,631 @@
+pub mod ability;
+pub mod archetype;
+pub mod assets;
+pub mod aura;
+pub mod boss;
+pub mod build_phase;
+pub mod combat_feedback;
+pub mod command;
+pub mod difficulty;
+pub mod economy;
+pub mod endless;
+pub mod enemy;
+pub mod grid;
+pub mod headless;
+pub mod input;
+pub mod macros;
+pub mod menu;
+pub mod path;
+pub mod player;
+pub mod replay;
+pub mod results;
+pub mod ron_file;
+pub mod save;
+#[cfg(test)]
+mod scenario;
+pub mod score;
+pub mod settings;
+pub mod speed;
+pub mod state_conditions;
+pub mod stats;
+pub mod synergy;
+pub mod tower;
+pub mod ui;
+pub mod veterancy;
+pub mod wave;
+
+use ability::AbilityPlugin;
+use archetype::ArchetypePlugin;
+use assets::{MAIN_LOOP, PATH_SPLINE_SAMPLES, is_loaded};
+use aura::{AuraPlugin, SupportAura, apply_support_auras};
+use bevy::{
+    app::{App, FixedUpdate, Plugin, Startup, Update},
+    asset::AssetServer,
+    audio::{AudioPlayer, PlaybackSettings},
+    core_pipeline::core_2d::Camera2d,
+    ecs::{
+        component::ComponentId,
+        entity::Entity,
+        query::{Or, With},
+        schedule::{Condition, IntoScheduleConfigs, common_conditions::resource_exists},
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::{error, info},
+    picking::mesh_picking::MeshPickingPlugin,
+    prelude::*,
+    render::camera::{OrthographicProjection, Projection, ScalingMode},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    state::{app::AppExtStates, condition::in_state, state::OnEnter},
+    transform::components::Transform,
+};
+use bevy_dev_tools::picking_debug::DebugPickingMode;
+use boss::BossPlugin;
+use build_phase::{BuildPhasePlugin, BuildPhaseUiPlugin};
+use combat_feedback::CombatFeedbackPlugin;
+use difficulty::DifficultyPlugin;
+use economy::{EconomyPlugin, EconomyRulesHandle, pay_wave_income};
+use endless::{EndlessPlugin, GeneratedWave, prepare_endless_wave, wave_definition};
+use enemy::{
+    DamageTaken, EnemyMoved, enemies_are_loaded, init_spawn_timer, plan_flight_path, regenerate,
+    setup_enemy_resources, spawn_enemy, update_enemy,
+};
+use grid::{
+    DefaultHexMaterial, GridEntity, GridEntry, GridIndex, GridPlugin, GridSet, HexGridColumns,
+    HexGridHeight, HexGridRenderRadius, HexGridRows, HexGridWidth, HexHashGrid, Path, PathEnd,
+    PathEndMaterial, PathMaterial, PathStart, PathStartMaterial,
+};
+use input::{InputPlugin, InputSet};
+use menu::{MenuPlugin, MenuSet, PauseMenu};
+use path::{
+    DefaultSinglePathFinder, HexPath, PathPlugin, PathPreviewPlugin, PathSet, SecondPath,
+    SinglePathFinder,
+    context::PathContext,
+    random_selected::RandomDijkstra,
+    regeneration::{FreeRelocation, PathRegenerationPolicy, TowerReach, find_path_keeping_towers},
+    spline::PathSpline,
+};
+use player::{GoldGained, on_gold_gained, setup_player};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use replay::ReplayPlugin;
+use results::{ResultsPlugin, ResultsUiPlugin};
+use save::SavePlugin;
+use score::ScorePlugin;
+use settings::SettingsPlugin;
+use speed::SpeedPlugin;
+use state_conditions::{advance_after_wave, change_state, new_path_next_wave, wave_done};
+use stats::{Range, RunRng, RunSeed, Wave};
+use synergy::{SynergyLinksPlugin, SynergyPlugin, SynergyRulesHandle, evaluate_synergies};
+use tower::{
+    Tower, TowerIndex, init_tower_resources, load_shot_sound, update_effective_stats,
+    update_projectiles, update_tower,
+};
+use ui::{UiMessage, UiOverlay};
+use veterancy::{VeterancyPlugin, VeterancyUiPlugin, promote_towers};
+use wave::{WaveSchedule, WaveScript, WaveScriptHandle, WaveScriptPlugin};
+
+/// The whole game, to be added on top of `DefaultPlugins`.
+pub struct RandomTdPlugin;
+
+impl Plugin for RandomTdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(SimulationPlugin);
+        app.add_plugins(RenderingPlugin);
+        app.add_plugins(SoundPlugin);
+        app.add_plugins(InterfacePlugin);
+        app.add_plugins(ScorePlugin);
+        app.add_plugins(SavePlugin);
+        app.add_plugins(ReplayPlugin);
+    }
+}
+
+/// Camera and the visuals drawn into the world: path preview, health bars,
+/// damage numbers, rank badges and synergy links.
+pub struct RenderingPlugin;
+
+impl Plugin for RenderingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PathPreviewPlugin);
+        app.add_plugins(CombatFeedbackPlugin);
+        app.add_plugins(SynergyLinksPlugin);
+        app.add_plugins(VeterancyUiPlugin);
+        app.add_systems(Startup, setup_camera.after(GridSet));
+        app.add_systems(
+            Update,
+            fit_camera_to_grid.run_if(resource_changed::<HexGridWidth>),
+        );
+    }
+}
+
+/// Background music and sound effects.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (play_music, load_shot_sound));
+    }
+}
+
+/// Menus, overlay and everything the player clicks or types.
+pub struct InterfacePlugin;
+
+impl Plugin for InterfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MeshPickingPlugin);
+        //app.add_plugins(DebugPickingPlugin);
+        app.add_plugins(InputPlugin);
+        app.add_plugins(UiOverlay);
+        app.add_plugins(ResultsUiPlugin);
+        app.add_plugins(SpeedPlugin);
+        app.add_plugins(BuildPhaseUiPlugin);
+        app.add_plugins(MenuPlugin);
+        //app.add_plugins(DebugUiOverlay);
+        app.insert_resource(DebugPickingMode::Normal);
+    }
+}
+
+/// Game rules and state wiring, without input, audio or UI.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(GridPlugin::default());
+        app.add_plugins(PathPlugin);
+        app.add_plugins(ArchetypePlugin);
+        app.add_plugins(WaveScriptPlugin);
+        app.add_plugins(AuraPlugin);
+        app.add_plugins(BossPlugin);
+        app.add_plugins(AbilityPlugin);
+        app.add_plugins(SettingsPlugin);
+        app.add_plugins(ResultsPlugin);
+        app.add_plugins(BuildPhasePlugin);
+        app.add_plugins(SynergyPlugin);
+        app.add_plugins(VeterancyPlugin);
+        app.add_plugins(EconomyPlugin);
+        app.add_plugins(DifficultyPlugin);
+        app.add_plugins(EndlessPlugin);
+        app.insert_resource(Wave(0));
+        app.init_resource::<RunSeed>();
+        app.init_resource::<RunRng>();
+        app.insert_state(GameState::Startup);
+        app.add_event::<DamageTaken>();
+        app.add_event::<GoldGained>();
+        app.add_event::<EnemyMoved>();
+        app.world_mut().register_component::<Tower>();
+        let id = app.world().component_id::<Tower>().unwrap();
+        app.insert_resource(TowerTargets(id));
+        app.add_observer(on_gold_gained);
+        app.add_observer(plan_flight_path);
+        app.add_systems(
+            OnEnter(GameState::Startup),
+            (init_tower_resources, setup_enemy_resources),
+        );
+        app.add_systems(
+            OnEnter(GameState::Loading),
+            ((setup_player), change_state(GameState::MainMenu)).chain(),
+        );
+        app.add_systems(
+            Update,
+            change_state(GameState::Loading)
+                .run_if(
+                    enemies_are_loaded
+                        .and(is_loaded::<EconomyRulesHandle, _>)
+                        .and(is_loaded::<SynergyRulesHandle, _>),
+                )
+                .in_set(StartupSet),
+        );
+        app.add_systems(
+            FixedUpdate,
+            update_effective_stats
+                .after(apply_support_auras)
+                .after(evaluate_synergies)
+                .after(promote_towers),
+        );
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            (reseed_run_rng, generate_path.run_if(not(path_ready))).chain(),
+        );
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            generate_second_path
+                .after(generate_path)
+                .after(prepare_endless_wave),
+        );
+        app.add_systems(
+            OnEnter(GameState::Wave),
+            (init_spawn_timer).in_set(DuringWave),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                spawn_enemy,
+                update_enemy,
+                regenerate,
+                update_tower,
+                update_projectiles,
+                // After spawning, so an enemy spawned this tick counts as alive.
+                change_state(GameState::AfterWave)
+                    .run_if(resource_exists::<WaveSchedule>.and(wave_done))
+                    .after(spawn_enemy),
+            )
+                .in_set(DuringWave),
+        );
+
+        app.add_systems(
+            OnEnter(GameState::AfterWave),
+            (
+                update_wave,
+                pay_wave_income,
+                remove_second_path,
+                cleanup_path.run_if(new_path_next_wave),
+                advance_after_wave,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            OnExit(GameState::GameOver),
+            (remove_second_path, cleanup_path).chain(),
+        );
+        app.add_systems(
+            OnExit(GameState::Victory),
+            (remove_second_path, cleanup_path).chain(),
+        );
+        app.configure_sets(
+            Update,
+            (
+                InputSet.before(GridSet),
+                PathSet.after(GridSet),
+                DuringWave.run_if(in_state(GameState::Wave)),
+                BeforeWave
+                    .run_if(in_state(GameState::BeforeWave).and(not(in_state(PauseMenu::Open)))),
+                AfterWave.run_if(in_state(GameState::AfterWave)),
+                GridSet.run_if(
+                    (in_state(GameState::Wave).or(in_state(GameState::BeforeWave)))
+                        .and(not(in_state(PauseMenu::Open))),
+                ),
+                StartupSet.run_if(in_state(GameState::Startup)),
+                MenuSet.run_if(
+                    in_state(GameState::MainMenu)
+                        .or(in_state(GameState::NewGame))
+                        .or(in_state(GameState::SettingsMenu)),
+                ),
+            ),
+        );
+        app.configure_sets(
+            FixedUpdate,
+            (
+                DuringWave.run_if(in_state(GameState::Wave)),
+                BeforeWave
+                    .run_if(in_state(GameState::BeforeWave).and(not(in_state(PauseMenu::Open)))),
+            ),
+        );
+    }
+}
+#[derive(Resource)]
+pub struct TowerTargets(pub ComponentId);
+#[derive(Clone, Copy, Hash, Debug, Default, States, PartialEq, Eq)]
+#[states(scoped_entities)]
+pub enum GameState {
+    #[default]
+    Startup,
+    Loading,
+    MainMenu,
+    NewGame,
+    SettingsMenu,
+    Wave,
+    BeforeWave,
+    AfterWave,
+    GameOver,
+    Victory,
+}
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct DuringWave;
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct BeforeWave;
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct AfterWave;
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct StartupSet;
+
+pub fn path_ready(query: Query<(), With<PathStart>>) -> bool {
+    !query.is_empty()
+}
+pub fn play_music(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let background = asset_server.load(MAIN_LOOP);
+    commands.spawn((
+        AudioPlayer::new(background),
+        PlaybackSettings::LOOP.with_volume(bevy::audio::Volume::Linear(0.2)),
+    ));
+}
+pub fn setup_camera(mut commands: Commands, width: Res<HexGridWidth>, _height: Res<HexGridHeight>) {
+    let mut projection = OrthographicProjection::default_2d();
+    projection.scaling_mode = ScalingMode::FixedHorizontal {
+        viewport_width: **width,
+    };
+    commands
+        .spawn(Camera2d)
+        .insert(Projection::Orthographic(projection))
+        .insert(Transform::from_xyz(0.0, 50.0, 0.0));
+}
+pub fn fit_camera_to_grid(width: Res<HexGridWidth>, projections: Query<&mut Projection>) {
+    for mut projection in projections {
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            ortho.scaling_mode = ScalingMode::FixedHorizontal {
+                viewport_width: **width,
+            };
+        }
+    }
+}
+#[allow(clippy::type_complexity)]
+pub fn cleanup_path(
+    mut commands: Commands,
+    path_endings: Query<
+        (Entity, &mut MeshMaterial2d<ColorMaterial>, &GridEntity),
+        Or<(With<PathStart>, With<PathEnd>, With<Path>)>,
+    >,
+    mut grid: ResMut<HexHashGrid>,
+    default_color: Res<DefaultHexMaterial>,
+) {
+    info!("removing path");
+    for (e, mut m, ge) in path_endings {
+        commands.entity(e).remove::<PathStart>();
+        commands.entity(e).remove::<PathEnd>();
+        commands.entity(e).remove::<Path>();
+        m.0 = default_color.0.clone();
+        grid[ge.0] = GridEntry::None;
+    }
+}
+
+pub fn update_wave(mut wave: ResMut<Wave>) {
+    wave.0 += 1;
+}
+
+pub fn reseed_run_rng(mut commands: Commands, seed: Res<RunSeed>, wave: Res<Wave>) {
+    commands.insert_resource(RunRng::for_wave(&seed, wave.0));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn generate_path(
+    mut commands: Commands,
+    grid: Res<HexHashGrid>,
+    rows: Res<HexGridRows>,
+    columns: Res<HexGridColumns>,
+    render_radius: Res<HexGridRenderRadius>,
+    policy: Res<PathRegenerationPolicy>,
+    previous: Option<Res<HexPath<GridIndex>>>,
+    towers: Query<(Entity, &TowerIndex, &Range, Has<SupportAura>), With<Tower>>,
+    seed: Res<RunSeed>,
+    wave: Res<Wave>,
+) {
+    let mut seeds = StdRng::seed_from_u64(seed.for_wave(wave.0));
+    let context = PathContext::from_args(&rows, &columns, &grid);
+    // Support towers buff other towers and do not need to reach the path.
+    let reach: Vec<TowerReach> = towers
+        .iter()
+        .filter(|(.., support)| !support)
+        .map(|(entity, index, range, _)| TowerReach {
+            entity,
+            position: index.to_world_pos(**render_radius),
+            range: range.0,
+        })
+        .collect();
+    for (tower, ..) in &towers {
+        commands.entity(tower).remove::<FreeRelocation>();
+    }
+    let found = find_path_keeping_towers(
+        || {
+            let seed = seeds.random();
+            DefaultSinglePathFinder::seeded(
+                RandomDijkstra {
+                    tile_size: **render_radius,
+                    seed: Some(seed),
+                },
+                seed,
+            )
+            .get_path(context)
+        },
+        policy.attempts,
+        &reach,
+        **render_radius,
+    );
+    let path = match (found, previous) {
+        (Some((pa, stranded)), _) => {
+            if !stranded.is_empty() {
+                for tower in &stranded {
+                    commands.entity(*tower).insert(FreeRelocation);
+                }
+                commands.trigger(UiMessage(format!(
+                    "{} tower(s) can no longer reach the path, select one and click an empty tile to move it for free",
+                    stranded.len()
+                )));
+            }
+            Some(pa)
+        }
+        (None, Some(previous)) => {
+            commands.trigger(UiMessage(
+                "No new path fits around your towers, keeping the previous one".to_string(),
+            ));
+            Some(previous.clone())
+        }
+        (None, None) => None,
+    };
+    if let Some(pa) = path {
+        commands.run_system_cached_with(place_path, pa);
+    } else {
+        error!("failed to find path");
+        commands.trigger(UiMessage(
+            "Failed to find a path for the enemies".to_string(),
+        ));
+    }
+}
+
+/// Marks the tiles of `pa` on the grid and makes it the current path.
+pub fn place_path(
+    In(pa): In<HexPath<GridIndex>>,
+    mut commands: Commands,
+    render_radius: Res<HexGridRenderRadius>,
+) {
+    commands.run_system_cached_with(mark_path_tiles, pa.clone());
+    commands.insert_resource(PathSpline::catmull_rom(
+        &pa,
+        **render_radius,
+        PATH_SPLINE_SAMPLES,
+    ));
+    commands.insert_resource(pa);
+}
+
+/// Marks the tiles of `pa` on the grid and makes it the wave's second path.
+pub fn place_second_path(
+    In(pa): In<HexPath<GridIndex>>,
+    mut commands: Commands,
+    render_radius: Res<HexGridRenderRadius>,
+) {
+    commands.run_system_cached_with(mark_path_tiles, pa.clone());
+    commands.insert_resource(SecondPath {
+        spline: PathSpline::catmull_rom(&pa, **render_radius, PATH_SPLINE_SAMPLES),
+        path: pa,
+    });
+}
+
+/// Marks the start, end and path tiles of `pa` on the grid and colours them.
+pub fn mark_path_tiles(
+    In(pa): In<HexPath<GridIndex>>,
+    mut commands: Commands,
+    mut grid: ResMut<HexHashGrid>,
+    mut grid_entities: Query<(Entity, &GridEntity, &mut MeshMaterial2d<ColorMaterial>)>,
+    path_material: Res<PathMaterial>,
+    path_start_material: Res<PathStartMaterial>,
+    path_end_material: Res<PathEndMaterial>,
+) {
+    grid_entities.iter_mut().for_each(|(e, entry, mut color)| {
+        if entry.0 == pa.start {
+            commands.entity(e).insert(PathStart);
+            color.0 = path_start_material.0.clone();
+        } else if entry.0 == pa.end {
+            commands.entity(e).insert(PathEnd);
+            color.0 = path_end_material.0.clone();
+        } else if pa.contains(&entry.0) {
+            commands.entity(e).insert(Path);
+            color.0 = path_material.0.clone();
+        }
+    });
+    for p in &pa.nodes {
+        if *p == pa.start {
+            grid[*p] = GridEntry::PathStart;
+        } else if *p == pa.end {
+            grid[*p] = GridEntry::PathEnd;
+        } else {
+            grid[*p] = GridEntry::Path
+        }
+    }
+}
+
+/// Lays a second path on waves that ask for one, avoiding the towers and the
+/// ends of the main path. It may cross the main path.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_second_path(
+    mut commands: Commands,
+    grid: Res<HexHashGrid>,
+    rows: Res<HexGridRows>,
+    columns: Res<HexGridColumns>,
+    render_radius: Res<HexGridRenderRadius>,
+    policy: Res<PathRegenerationPolicy>,
+    script: Res<WaveScriptHandle>,
+    scripts: Res<Assets<WaveScript>>,
+    generated: Option<Res<GeneratedWave>>,
+    second: Option<Res<SecondPath>>,
+    seed: Res<RunSeed>,
+    wave: Res<Wave>,
+) {
+    let wanted = wave_definition(wave.0, scripts.get(&script.0), generated.as_deref())
+        .is_some_and(|definition| definition.second_path);
+    if !wanted || second.is_some() {
+        return;
+    }
+    let mut seeds = StdRng::seed_from_u64(seed.for_wave(wave.0).rotate_left(29));
+    let context = PathContext::from_args(&rows, &columns, &grid);
+    let found = (0..policy.attempts).find_map(|_| {
+        let seed = seeds.random();
+        DefaultSinglePathFinder::seeded(
+            RandomDijkstra {
+                tile_size: **render_radius,
+                seed: Some(seed),
+            },
+            seed,
+        )
+        .get_path(context)
+    });
+    if let Some(pa) = found {
+        info!("placing second path");
+        commands.run_system_cached_with(place_second_path, pa);
+    } else {
+        warn!("failed to find a second path");
+        commands.trigger(UiMessage(
+            "No second path fits on the board, all enemies take the main path".to_string(),
+        ));
+    }
+}
+
+/// Clears the tiles only the second path used and drops it.
+#[allow(clippy::type_complexity)]
+pub fn remove_second_path(
+    mut commands: Commands,
+    second: Option<Res<SecondPath>>,
+    main: Option<Res<HexPath<GridIndex>>>,
+    tiles: Query<(Entity, &mut MeshMaterial2d<ColorMaterial>, &GridEntity)>,
+    mut grid: ResMut<HexHashGrid>,
+    default_color: Res<DefaultHexMaterial>,
+) {
+    let Some(second) = second else {
+        return;
+    };
+    info!("removing second path");
+    let only_second = |index: &GridIndex| {
+        second.path.contains(index) && !main.as_ref().is_some_and(|main| main.contains(index))
+    };
+    for (e, mut m, ge) in tiles {
+        if only_second(&ge.0) {
+            commands.entity(e).remove::<(PathStart, PathEnd, Path)>();
+            m.0 = default_color.0.clone();
+            grid[ge.0] = GridEntry::None;
+        }
+    }
+    commands.remove_resource::<SecondPath>();
+}
+
+/// Value following `flag` on the command line, if any.
+pub fn flag_value(mut args: impl Iterator<Item = String>, flag: &str) -> Option<String> {
+    args.find(|a| a == flag)?;
+    args.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_value_follows_the_flag() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            flag_value(
+                args(&["random_td", "--replay", "run.ron"]).into_iter(),
+                "--replay"
+            ),
+            Some("run.ron".to_string())
+        );
+        assert_eq!(
+            flag_value(args(&["random_td"]).into_iter(), "--replay"),
+            None
+        );
+        assert_eq!(
+            flag_value(args(&["random_td", "--replay"]).into_iter(), "--replay"),
+            None
+        );
+        assert_eq!(
+            flag_value(
+                args(&["random_td", "--replay", "run.ron"]).into_iter(),
+                "--headless"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,568 @@
+use bevy::{
+    ecs::{relationship::RelatedSpawnerCommands, system::IntoObserverSystem},
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+use crate::{
+    GameState,
+    assets::FONT_SIZE,
+    difficulty::{Difficulty, StartRun},
+    grid::MapSize,
+    input::InputSet,
+    path::PathMode,
+    results::{AbandonRun, RestartRun},
+    save::{LoadGame, has_save},
+    settings::{SettingToggle, Settings},
+    stats::RunSeed,
+    tower::SelectedTower,
+    ui::UiFont,
+};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<PauseMenu>();
+        app.init_resource::<SeedInput>();
+        app.add_observer(return_to_main_menu);
+        app.add_observer(close_pause_menu);
+        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu);
+        app.add_systems(
+            OnEnter(GameState::NewGame),
+            (reroll_seed, spawn_new_game_menu).chain(),
+        );
+        app.add_systems(OnEnter(GameState::SettingsMenu), spawn_settings_menu);
+        app.add_systems(OnEnter(PauseMenu::Open), (pause_for_menu, spawn_pause_menu));
+        app.add_systems(OnExit(PauseMenu::Open), resume_after_menu);
+        app.add_systems(
+            Update,
+            toggle_pause_menu
+                .before(InputSet)
+                .run_if(in_state(GameState::Wave).or(in_state(GameState::BeforeWave))),
+        );
+        app.add_systems(Update, update_setting_toggles);
+        app.add_systems(Update, type_seed.run_if(in_state(GameState::NewGame)));
+        app.add_systems(
+            Update,
+            (highlight_choices, update_seed_label).in_set(MenuSet),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MenuSet;
+
+/// In-game menu, only present while a run is being played.
+#[derive(SubStates, Clone, Copy, Default, Debug, Hash, PartialEq, Eq)]
+#[source(GameState = GameState::Wave | GameState::BeforeWave | GameState::AfterWave)]
+#[states(scoped_entities)]
+pub enum PauseMenu {
+    #[default]
+    Closed,
+    Open,
+}
+
+/// Ends the current run and goes back to the main menu.
+#[derive(Event)]
+pub struct ReturnToMainMenu;
+
+/// Whether the game was already paused when the pause menu opened.
+#[derive(Resource)]
+pub struct PausedBeforeMenu(pub bool);
+
+/// Option of the new game menu, highlighted while selected.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuChoice {
+    Map(MapSize),
+    Difficulty(Difficulty),
+    PathMode(PathMode),
+}
+
+#[derive(Component)]
+pub struct SeedLabel;
+
+/// Hex digits typed on the new game menu, replacing the rolled seed.
+#[derive(Resource, Default, Debug)]
+pub struct SeedInput(pub String);
+
+static BUTTON_COLOR: Color = Color::hsla(0.0, 0.0, 0.25, 1.0);
+static SELECTED_BUTTON_COLOR: Color = Color::hsla(45.0, 0.8, 0.35, 1.0);
+static DISABLED_BUTTON_COLOR: Color = Color::hsla(0.0, 0.0, 0.15, 1.0);
+static DISABLED_TEXT_COLOR: Color = Color::hsla(0.0, 0.0, 0.5, 1.0);
+static MENU_BACKGROUND: Color = Color::hsla(0.0, 0.0, 0.0, 0.7);
+
+fn menu_root(state: impl States + Copy) -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(8.0),
+            ..Default::default()
+        },
+        BackgroundColor(MENU_BACKGROUND),
+        StateScoped(state),
+    )
+}
+
+fn button_node() -> Node {
+    Node {
+        min_width: Val::Px(220.0),
+        justify_content: JustifyContent::Center,
+        padding: UiRect::axes(Val::Px(16.0), Val::Px(6.0)),
+        ..Default::default()
+    }
+}
+
+fn spawn_button<M>(
+    builder: &mut RelatedSpawnerCommands<ChildOf>,
+    label: impl Into<String>,
+    font: &TextFont,
+    on_click: impl IntoObserverSystem<Pointer<Click>, (), M>,
+) {
+    builder
+        .spawn((button_node(), Button, BackgroundColor(BUTTON_COLOR)))
+        .with_child((Text::new(label), font.clone(), Pickable::IGNORE))
+        .observe(on_click);
+}
+
+fn spawn_setting_toggles(builder: &mut RelatedSpawnerCommands<ChildOf>, font: &TextFont) {
+    for toggle in SettingToggle::ALL {
+        builder
+            .spawn((button_node(), Button, BackgroundColor(BUTTON_COLOR)))
+            .with_child((Text::new(""), font.clone(), toggle, Pickable::IGNORE))
+            .observe(
+                move |_: Trigger<Pointer<Click>>, mut settings: ResMut<Settings>| {
+                    toggle.flip(&mut settings);
+                },
+            );
+    }
+}
+
+fn set_state(state: GameState) -> impl Fn(Trigger<Pointer<Click>>, ResMut<NextState<GameState>>) {
+    move |_, mut next_state| next_state.set(state)
+}
+
+fn quit(_: Trigger<Pointer<Click>>, mut exit: EventWriter<AppExit>) {
+    exit.write(AppExit::Success);
+}
+
+fn menu_font(font: &UiFont) -> TextFont {
+    TextFont::default()
+        .with_font(font.0.clone())
+        .with_font_size(FONT_SIZE)
+}
+
+pub fn spawn_main_menu(mut commands: Commands, font: Res<UiFont>) {
+    let text_font = menu_font(&font);
+    commands
+        .spawn(menu_root(GameState::MainMenu))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("Random TD"),
+                text_font.clone().with_font_size(FONT_SIZE * 2.5),
+            ));
+            spawn_button(
+                builder,
+                "New game",
+                &text_font,
+                set_state(GameState::NewGame),
+            );
+            if has_save() {
+                spawn_button(
+                    builder,
+                    "Continue",
+                    &text_font,
+                    |_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                        commands.trigger(LoadGame);
+                    },
+                );
+            } else {
+                builder
+                    .spawn((button_node(), BackgroundColor(DISABLED_BUTTON_COLOR)))
+                    .with_child((
+                        Text::new("Continue"),
+                        text_font.clone(),
+                        TextColor(DISABLED_TEXT_COLOR),
+                    ));
+            }
+            spawn_button(
+                builder,
+                "Settings",
+                &text_font,
+                set_state(GameState::SettingsMenu),
+            );
+            spawn_button(builder, "Quit", &text_font, quit);
+        });
+}
+
+pub fn reroll_seed(mut seed: ResMut<RunSeed>, mut input: ResMut<SeedInput>) {
+    *seed = RunSeed::default();
+    input.0.clear();
+}
+
+/// Sets the seed from hex digits typed on the new game menu.
+pub fn type_seed(
+    mut keys: EventReader<KeyboardInput>,
+    mut input: ResMut<SeedInput>,
+    mut seed: ResMut<RunSeed>,
+) {
+    for key in keys.read().filter(|k| k.state == ButtonState::Pressed) {
+        match &key.logical_key {
+            Key::Character(c)
+                if c.chars().all(|c| c.is_ascii_hexdigit()) && input.0.len() + c.len() <= 16 =>
+            {
+                input.0.push_str(&c.to_lowercase());
+            }
+            Key::Backspace => {
+                input.0.pop();
+            }
+            _ => continue,
+        }
+        if let Ok(typed) = u64::from_str_radix(&input.0, 16) {
+            *seed = RunSeed(typed);
+        }
+    }
+}
+
+pub fn spawn_new_game_menu(mut commands: Commands, font: Res<UiFont>) {
+    let text_font = menu_font(&font);
+    let row = || Node {
+        column_gap: Val::Px(8.0),
+        ..Default::default()
+    };
+    let choice = |builder: &mut RelatedSpawnerCommands<ChildOf>, name: &str, choice: MenuChoice| {
+        builder
+            .spawn((
+                Node {
+                    padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                    ..Default::default()
+                },
+                Button,
+                BackgroundColor(BUTTON_COLOR),
+                choice,
+            ))
+            .with_child((Text::new(name), text_font.clone(), Pickable::IGNORE))
+            .observe(
+                move |_: Trigger<Pointer<Click>>,
+                      mut map: ResMut<MapSize>,
+                      mut difficulty: ResMut<Difficulty>,
+                      mut path_mode: ResMut<PathMode>| match choice {
+                    MenuChoice::Map(m) => *map = m,
+                    MenuChoice::Difficulty(d) => *difficulty = d,
+                    MenuChoice::PathMode(p) => *path_mode = p,
+                },
+            );
+    };
+    commands
+        .spawn(menu_root(GameState::NewGame))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("New game"),
+                text_font.clone().with_font_size(FONT_SIZE * 2.0),
+            ));
+            builder.spawn((Text::new("map"), text_font.clone()));
+            builder.spawn(row()).with_children(|builder| {
+                for map in MapSize::ALL {
+                    let (columns, rows) = map.columns_and_rows();
+                    let name = format!("{} {columns}x{rows}", map.name());
+                    choice(builder, &name, MenuChoice::Map(map));
+                }
+            });
+            builder.spawn((Text::new("difficulty"), text_font.clone()));
+            builder.spawn(row()).with_children(|builder| {
+                for difficulty in Difficulty::ALL {
+                    choice(
+                        builder,
+                        difficulty.name(),
+                        MenuChoice::Difficulty(difficulty),
+                    );
+                }
+            });
+            builder.spawn((Text::new("new path"), text_font.clone()));
+            builder.spawn(row()).with_children(|builder| {
+                for mode in PathMode::ALL {
+                    choice(builder, &mode.name(), MenuChoice::PathMode(mode));
+                }
+            });
+            builder.spawn(row()).with_children(|builder| {
+                builder.spawn((Text::new(""), text_font.clone(), SeedLabel));
+                builder
+                    .spawn((
+                        Node {
+                            padding: UiRect::axes(Val::Px(12.0), Val::Px(0.0)),
+                            ..Default::default()
+                        },
+                        Button,
+                        BackgroundColor(BUTTON_COLOR),
+                    ))
+                    .with_child((Text::new("reroll"), text_font.clone(), Pickable::IGNORE))
+                    .observe(
+                        |_: Trigger<Pointer<Click>>,
+                         seed: ResMut<RunSeed>,
+                         input: ResMut<SeedInput>| {
+                            reroll_seed(seed, input);
+                        },
+                    );
+            });
+            builder.spawn((Text::new("or type a seed"), text_font.clone()));
+            spawn_button(
+                builder,
+                "Start",
+                &text_font,
+                |_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(StartRun);
+                },
+            );
+            spawn_button(builder, "Back", &text_font, set_state(GameState::MainMenu));
+        });
+}
+
+pub fn spawn_settings_menu(mut commands: Commands, font: Res<UiFont>) {
+    let text_font = menu_font(&font);
+    commands
+        .spawn(menu_root(GameState::SettingsMenu))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("Settings"),
+                text_font.clone().with_font_size(FONT_SIZE * 2.0),
+            ));
+            spawn_setting_toggles(builder, &text_font);
+            spawn_button(builder, "Back", &text_font, set_state(GameState::MainMenu));
+        });
+}
+
+pub fn spawn_pause_menu(mut commands: Commands, font: Res<UiFont>) {
+    let text_font = menu_font(&font);
+    commands
+        .spawn(menu_root(PauseMenu::Open))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new("Paused"),
+                text_font.clone().with_font_size(FONT_SIZE * 2.0),
+            ));
+            spawn_button(
+                builder,
+                "Resume [Esc]",
+                &text_font,
+                |_: Trigger<Pointer<Click>>, mut next: ResMut<NextState<PauseMenu>>| {
+                    next.set(PauseMenu::Closed);
+                },
+            );
+            spawn_setting_toggles(builder, &text_font);
+            spawn_button(
+                builder,
+                "Restart",
+                &text_font,
+                |_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(RestartRun);
+                },
+            );
+            spawn_button(
+                builder,
+                "Main menu",
+                &text_font,
+                |_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(ReturnToMainMenu);
+                },
+            );
+            spawn_button(builder, "Quit", &text_font, quit);
+        });
+}
+
+pub fn toggle_pause_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    selected: Res<SelectedTower>,
+    state: Option<Res<State<PauseMenu>>>,
+    mut next: ResMut<NextState<PauseMenu>>,
+) {
+    let Some(state) = state else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    match state.get() {
+        PauseMenu::Open => next.set(PauseMenu::Closed),
+        // Escape first clears the tower selection.
+        PauseMenu::Closed if selected.is_none() => next.set(PauseMenu::Open),
+        PauseMenu::Closed => {}
+    }
+}
+
+pub fn pause_for_menu(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    commands.insert_resource(PausedBeforeMenu(time.is_paused()));
+    time.pause();
+}
+
+pub fn resume_after_menu(
+    mut time: ResMut<Time<Virtual>>,
+    paused_before: Option<Res<PausedBeforeMenu>>,
+) {
+    if !paused_before.is_some_and(|p| p.0) {
+        time.unpause();
+    }
+}
+
+pub fn return_to_main_menu(
+    _trigger: Trigger<ReturnToMainMenu>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    commands.trigger(AbandonRun);
+    next_state.set(GameState::MainMenu);
+}
+
+/// A restarted run starts with the pause menu closed.
+pub fn close_pause_menu(_trigger: Trigger<RestartRun>, mut next: ResMut<NextState<PauseMenu>>) {
+    next.set(PauseMenu::Closed);
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_setting_toggles(
+    settings: Res<Settings>,
+    toggles: Query<(Ref<SettingToggle>, &mut Text)>,
+) {
+    for (toggle, mut text) in toggles {
+        if settings.is_changed() || toggle.is_added() {
+            text.0 = toggle.label(&settings);
+        }
+    }
+}
+
+pub fn highlight_choices(
+    map: Res<MapSize>,
+    difficulty: Res<Difficulty>,
+    path_mode: Res<PathMode>,
+    choices: Query<(&MenuChoice, &mut BackgroundColor)>,
+) {
+    for (choice, mut color) in choices {
+        let selected = match choice {
+            MenuChoice::Map(m) => *m == *map,
+            MenuChoice::Difficulty(d) => *d == *difficulty,
+            MenuChoice::PathMode(p) => *p == *path_mode,
+        };
+        color.0 = if selected {
+            SELECTED_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
+
+pub fn update_seed_label(
+    seed: Res<RunSeed>,
+    input: Res<SeedInput>,
+    labels: Query<&mut Text, With<SeedLabel>>,
+) {
+    for mut text in labels {
+        text.0 = if input.0.is_empty() {
+            format!("seed: {:016x}", seed.0)
+        } else {
+            format!("seed: {}_", input.0)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{MinimalPlugins, state::app::StatesPlugin};
+
+    use super::*;
+
+    #[test]
+    fn pause_menu_pauses_and_restores_the_game() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin));
+        app.insert_state(GameState::BeforeWave);
+        app.add_sub_state::<PauseMenu>();
+        app.add_systems(OnEnter(PauseMenu::Open), pause_for_menu);
+        app.add_systems(OnExit(PauseMenu::Open), resume_after_menu);
+        app.update();
+
+        let set = |app: &mut App, state| {
+            app.world_mut()
+                .resource_mut::<NextState<PauseMenu>>()
+                .set(state);
+            app.update();
+        };
+        set(&mut app, PauseMenu::Open);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+        set(&mut app, PauseMenu::Closed);
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        set(&mut app, PauseMenu::Open);
+        set(&mut app, PauseMenu::Closed);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+        set(&mut app, PauseMenu::Open);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::MainMenu);
+        app.update();
+        assert!(app.world().get_resource::<State<PauseMenu>>().is_none());
+    }
+
+    #[test]
+    fn typed_hex_digits_set_the_seed() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<KeyboardInput>();
+        app.init_resource::<SeedInput>();
+        app.insert_resource(RunSeed(0));
+        app.add_systems(Update, type_seed);
+
+        let press = |app: &mut App, key: Key| {
+            app.world_mut().send_event(KeyboardInput {
+                key_code: KeyCode::KeyA,
+                logical_key: key,
+                state: ButtonState::Pressed,
+                text: None,
+                repeat: false,
+                window: Entity::PLACEHOLDER,
+            });
+        };
+        for c in ["1", "f", "x", "A"] {
+            press(&mut app, Key::Character(c.into()));
+        }
+        app.update();
+        assert_eq!(app.world().resource::<SeedInput>().0, "1fa");
+        assert_eq!(app.world().resource::<RunSeed>().0, 0x1fa);
+
+        press(&mut app, Key::Backspace);
+        app.update();
+        assert_eq!(app.world().resource::<RunSeed>().0, 0x1f);
+    }
+
+    #[test]
+    fn restart_closes_the_pause_menu() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin));
+        app.insert_state(GameState::BeforeWave);
+        app.add_sub_state::<PauseMenu>();
+        app.add_observer(close_pause_menu);
+        app.add_systems(OnEnter(PauseMenu::Open), pause_for_menu);
+        app.add_systems(OnExit(PauseMenu::Open), resume_after_menu);
+        app.update();
+
+        app.world_mut()
+            .resource_mut::<NextState<PauseMenu>>()
+            .set(PauseMenu::Open);
+        app.update();
+        app.world_mut().trigger(RestartRun);
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<PauseMenu>>().get(),
+            PauseMenu::Closed
+        );
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    }
+}
@@ -3,22 +3,45 @@ pub mod context;
 pub mod dijkstra;
 pub mod random;
 pub mod random_selected;
+pub mod regeneration;
 pub mod resolver;
+pub mod spline;
 pub mod steps;
 
-use crate::grid::GridIndex;
+use crate::{
+    GameState,
+    assets::{PATH_ARROW_COLOR, PATH_ARROW_SPACING, PATH_ARROW_SPEED, PATH_PREVIEW_COLOR},
+    generate_path,
+    grid::{GridIndex, HexGridRenderRadius},
+};
 use bevy::{
-    app::Plugin,
-    asset::Handle,
-    ecs::{component::Component, resource::Resource, schedule::SystemSet},
+    app::{Plugin, Startup, Update},
+    asset::{Assets, Handle},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Added,
+        resource::Resource,
+        schedule::{IntoScheduleConfigs, SystemSet},
+        system::{Commands, Query, Res, ResMut},
+    },
     log::info,
-    math::Vec2,
+    math::{
+        Quat, Vec2,
+        primitives::{Rectangle, Triangle2d},
+    },
     platform::collections::HashMap,
-    sprite::ColorMaterial,
+    render::mesh::{Mesh, Mesh2d},
+    sprite::{ColorMaterial, MeshMaterial2d},
+    state::{state::OnEnter, state_scoped::StateScoped},
+    time::Time,
+    transform::components::Transform,
 };
 use context::{DistanceCache, PathContext};
 use dijkstra::{DistanceValue, Indexable, TileStateCache};
-use random::RandomSelector;
+use random::{RandomSelector, SeededSelector};
+use regeneration::PathRegenerationPolicy;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component, Debug)]
 pub struct PathSegment {
@@ -30,31 +53,175 @@ pub struct PathSegment {
 pub struct PathPlugin;
 
 impl Plugin for PathPlugin {
-    fn build(&self, _app: &mut bevy::app::App) {
-        // app.add_systems(Update, update_segments.in_set(PathSet));
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_resource::<PathMode>();
+        app.init_resource::<PathRegenerationPolicy>();
+    }
+}
+
+/// Segments and moving arrows previewing the path during the build phase.
+pub struct PathPreviewPlugin;
+
+impl Plugin for PathPreviewPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(Startup, init_path_preview_assets);
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            spawn_path_preview.after(generate_path),
+        );
+        app.add_systems(
+            Update,
+            (update_segments, animate_path_arrows).in_set(PathSet),
+        );
     }
 }
 #[derive(SystemSet, PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub struct PathSet;
 
-// fn update_segments(
-//     mut commands: Commands,
-//     query: Query<(Entity, &PathSegment), Added<PathSegment>>,
-//     mut meshes: ResMut<Assets<Mesh>>,
-// ) {
-//     for (e, p) in query {
-//         let square = Rectangle::new((p.start.x - p.end.x).abs(), (p.start.y - p.end.y).abs());
-//         let m = meshes.add(square.mesh());
-//         info!("inserting {p:?}");
-//         commands.entity(e).insert((
-//             Mesh2d(m),
-//             MeshMaterial2d(p.color.clone()),
-//             Transform::from_xyz(p.start.x, p.start.y, 20.0),
-//         ));
-//     }
-// }
-
-#[derive(Resource, Debug)]
+/// When a new path is generated.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathMode {
+    /// One path for the whole game.
+    Fixed,
+    #[default]
+    EveryWave,
+    EveryNWaves(u32),
+}
+
+impl PathMode {
+    /// Modes offered in the new game menu.
+    pub const ALL: [PathMode; 3] = [
+        PathMode::EveryWave,
+        PathMode::EveryNWaves(3),
+        PathMode::Fixed,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            PathMode::Fixed => "fixed".to_string(),
+            PathMode::EveryWave => "every wave".to_string(),
+            PathMode::EveryNWaves(n) => format!("every {n} waves"),
+        }
+    }
+
+    /// Whether wave `wave`, counting from zero, is played on a new path.
+    pub fn new_path_for(&self, wave: u32) -> bool {
+        match *self {
+            PathMode::Fixed => wave == 0,
+            PathMode::EveryWave => true,
+            PathMode::EveryNWaves(n) => wave.is_multiple_of(n.max(1)),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct PathPreviewAssets {
+    pub segment_material: Handle<ColorMaterial>,
+    pub arrow_mesh: Handle<Mesh>,
+    pub arrow_material: Handle<ColorMaterial>,
+}
+
+/// Arrow travelling along the previewed path, `offset` world units ahead of
+/// the first one.
+#[derive(Component)]
+pub struct PathArrow {
+    pub offset: f32,
+}
+
+static PATH_PREVIEW_WIDTH: f32 = 6.0;
+static PATH_PREVIEW_Z: f32 = 3.0;
+
+pub fn init_path_preview_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(PathPreviewAssets {
+        segment_material: materials.add(PATH_PREVIEW_COLOR),
+        arrow_mesh: meshes.add(Triangle2d::new(
+            Vec2::new(8.0, 0.0),
+            Vec2::new(-6.0, 6.0),
+            Vec2::new(-6.0, -6.0),
+        )),
+        arrow_material: materials.add(PATH_ARROW_COLOR),
+    });
+}
+
+/// Spawns segments and arrows over the upcoming path, removed once the wave starts.
+pub fn spawn_path_preview(
+    mut commands: Commands,
+    path: Option<Res<HexPath<GridIndex>>>,
+    spline: Option<Res<spline::PathSpline>>,
+    assets: Res<PathPreviewAssets>,
+    size: Res<HexGridRenderRadius>,
+) {
+    let (Some(path), Some(spline)) = (path, spline) else {
+        return;
+    };
+    for pair in path.nodes.windows(2) {
+        commands.spawn((
+            PathSegment {
+                start: pair[0].to_world_pos(**size),
+                end: pair[1].to_world_pos(**size),
+                color: assets.segment_material.clone(),
+            },
+            StateScoped(GameState::BeforeWave),
+        ));
+    }
+    let arrows = (spline.length() / PATH_ARROW_SPACING).ceil() as usize;
+    for i in 0..arrows {
+        commands.spawn((
+            PathArrow {
+                offset: i as f32 * PATH_ARROW_SPACING,
+            },
+            Mesh2d(assets.arrow_mesh.clone()),
+            MeshMaterial2d(assets.arrow_material.clone()),
+            Transform::from_xyz(0.0, 0.0, PATH_PREVIEW_Z + 0.1),
+            StateScoped(GameState::BeforeWave),
+        ));
+    }
+}
+
+fn update_segments(
+    mut commands: Commands,
+    query: Query<(Entity, &PathSegment), Added<PathSegment>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (e, p) in query {
+        let delta = p.end - p.start;
+        let m = meshes.add(Rectangle::new(delta.length(), PATH_PREVIEW_WIDTH));
+        let center = (p.start + p.end) / 2.0;
+        commands.entity(e).insert((
+            Mesh2d(m),
+            MeshMaterial2d(p.color.clone()),
+            Transform::from_xyz(center.x, center.y, PATH_PREVIEW_Z)
+                .with_rotation(Quat::from_rotation_z(delta.to_angle())),
+        ));
+    }
+}
+
+fn animate_path_arrows(
+    arrows: Query<(&PathArrow, &mut Transform)>,
+    spline: Option<Res<spline::PathSpline>>,
+    time: Res<Time>,
+) {
+    let Some(spline) = spline else {
+        return;
+    };
+    let length = spline.length();
+    if length <= 0.0 {
+        return;
+    }
+    for (arrow, mut transform) in arrows {
+        let distance = (arrow.offset + time.elapsed_secs() * PATH_ARROW_SPEED) % length;
+        let position = spline.position_at(distance);
+        let direction = spline.position_at(distance + 1.0) - position;
+        transform.translation = position.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(direction.to_angle());
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct HexPath<I: Indexable> {
     pub nodes: Vec<I>,
     pub start: I,
@@ -75,6 +242,14 @@ impl<I: Indexable> HexPath<I> {
     }
 }
 
+/// Extra path laid for a wave with `WaveDefinition::second_path`. Enemies
+/// spawned at its start follow its spline instead of the `PathSpline`.
+#[derive(Resource, Debug, Clone)]
+pub struct SecondPath {
+    pub path: HexPath<GridIndex>,
+    pub spline: spline::PathSpline,
+}
+
 pub trait StartSelector {
     fn get_start(&self, context: PathContext<'_>) -> Option<GridIndex>;
 }
@@ -134,6 +309,16 @@ impl<A: SinglePathAlgorithm> DefaultSinglePathFinder<RandomSelector, RandomSelec
     }
 }
 
+impl<A: SinglePathAlgorithm> DefaultSinglePathFinder<SeededSelector, SeededSelector, A> {
+    pub fn seeded(algo: A, seed: u64) -> Self {
+        Self {
+            s: SeededSelector { seed },
+            e: SeededSelector { seed },
+            a: algo,
+        }
+    }
+}
+
 impl<S: StartSelector, E: EndSelector, A: SinglePathAlgorithm> SinglePathFinder<S, E, A>
     for DefaultSinglePathFinder<S, E, A>
 {
@@ -150,3 +335,16 @@ impl<S: StartSelector, E: EndSelector, A: SinglePathAlgorithm> SinglePathFinder<
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PathMode;
+
+    #[test]
+    fn path_modes_pick_waves_with_new_paths() {
+        let waves = |mode: PathMode| (0..7).filter(|w| mode.new_path_for(*w)).collect::<Vec<_>>();
+        assert_eq!(waves(PathMode::Fixed), vec![0]);
+        assert_eq!(waves(PathMode::EveryWave), (0..7).collect::<Vec<_>>());
+        assert_eq!(waves(PathMode::EveryNWaves(3)), vec![0, 3, 6]);
+    }
+}
@@ -1,4 +1,4 @@
-use rand::{Rng, rng, seq::IteratorRandom};
+use rand::{Rng, SeedableRng, rng, rngs::StdRng, seq::IteratorRandom};
 
 use crate::grid::GridIndex;
 
@@ -27,6 +27,32 @@ impl EndSelector for RandomSelector {
     }
 }
 
+/// Picks the same start and end for the same seed and grid.
+#[derive(Debug, Clone, Copy)]
+pub struct SeededSelector {
+    pub seed: u64,
+}
+
+impl StartSelector for SeededSelector {
+    fn get_start(&self, context: PathContext<'_>) -> Option<GridIndex> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        context
+            .iter_start_column()
+            .filter(|i| context.can_be_path_ending(*i))
+            .choose(&mut rng)
+    }
+}
+
+impl EndSelector for SeededSelector {
+    fn get_end(&self, context: PathContext<'_>) -> Option<GridIndex> {
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(1));
+        context
+            .iter_end_column()
+            .filter(|i| context.can_be_path_ending(*i))
+            .choose(&mut rng)
+    }
+}
+
 pub fn choose_from_vec<T: Clone, R: Rng + ?Sized>(vec: &mut Vec<T>, rng: &mut R) -> Option<T> {
     let ele = vec.iter().cloned().enumerate().choose(rng);
     if let Some(t) = ele {
@@ -2,7 +2,7 @@ use bevy::{
     log::info,
     platform::collections::{HashMap, HashSet},
 };
-use rand::{random_range, rng, seq::IteratorRandom};
+use rand::{Rng, SeedableRng, rng, rngs::StdRng, seq::IteratorRandom};
 
 use crate::grid::{GridDirections, GridIndex};
 
@@ -59,6 +59,8 @@ impl DistanceFunction<GridIndex, f32> for WorldDistance {
 }
 pub struct RandomDijkstra {
     pub tile_size: f32,
+    /// Makes the path reproducible, a random one is used if `None`.
+    pub seed: Option<u64>,
 }
 
 pub fn try_get_path<TS: TileStateCache<GridIndex>>(
@@ -89,12 +91,15 @@ impl SinglePathAlgorithm for RandomDijkstra {
         start: crate::grid::GridIndex,
         end: crate::grid::GridIndex,
     ) -> Option<HexPath<GridIndex>> {
-        let mut rng = rng();
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(&mut rng()),
+        };
         let mut path = vec![];
         let mut tile_state = context.tile_state(start, end);
 
         let dijkstra = Dijkstra;
-        let num_points = random_range(1..=3usize);
+        let num_points = rng.random_range(1..=3usize);
         let mut c_start = start;
         let mut i = 0;
         let upper_boundary = 100;
@@ -146,7 +151,9 @@ mod tests {
 
     use crate::{
         grid::{HexGridColumns, HexGridRows, HexHashGrid},
-        path::{SinglePathAlgorithm, context::PathContext},
+        path::{
+            DefaultSinglePathFinder, SinglePathAlgorithm, SinglePathFinder, context::PathContext,
+        },
     };
 
     use super::*;
@@ -163,7 +170,10 @@ mod tests {
 
     #[test]
     fn random_dijkstra_should_work() {
-        let dijkstra = RandomDijkstra { tile_size: 50.0 };
+        let dijkstra = RandomDijkstra {
+            tile_size: 50.0,
+            seed: None,
+        };
         let grid = create_test_data();
         let column = hex_column();
         let rows = hex_rows();
@@ -175,6 +185,27 @@ mod tests {
         assert!(path.is_some());
     }
 
+    #[test]
+    fn seeded_paths_are_reproducible() {
+        let grid = create_test_data();
+        let column = hex_column();
+        let rows = hex_rows();
+        let context = PathContext::from_args(&rows, &column, &grid);
+        let path = |seed| {
+            DefaultSinglePathFinder::seeded(
+                RandomDijkstra {
+                    tile_size: 50.0,
+                    seed: Some(seed),
+                },
+                seed,
+            )
+            .get_path(context)
+            .unwrap()
+            .nodes
+        };
+        assert_eq!(path(42), path(42));
+    }
+
     #[test]
     fn dijkstra_data_should_work() {
         let dijkstra = Dijkstra;
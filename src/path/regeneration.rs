@@ -0,0 +1,142 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity, resource::Resource},
+    math::Vec2,
+};
+
+use super::HexPath;
+use crate::grid::GridIndex;
+
+/// How hard path generation tries to keep already placed towers useful.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PathRegenerationPolicy {
+    /// Paths generated before settling for the one stranding the fewest towers.
+    pub attempts: u32,
+}
+
+impl Default for PathRegenerationPolicy {
+    fn default() -> Self {
+        Self { attempts: 20 }
+    }
+}
+
+/// Tower that no longer reaches the path and can be moved for free.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FreeRelocation;
+
+/// Position and range of a placed tower.
+#[derive(Clone, Copy, Debug)]
+pub struct TowerReach {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub range: f32,
+}
+
+/// Towers without a single path tile within their range.
+pub fn stranded_towers(
+    path: &HexPath<GridIndex>,
+    towers: &[TowerReach],
+    tile_size: f32,
+) -> Vec<Entity> {
+    towers
+        .iter()
+        .filter(|t| {
+            !path
+                .nodes
+                .iter()
+                .any(|n| n.to_world_pos(tile_size).distance(t.position) <= t.range)
+        })
+        .map(|t| t.entity)
+        .collect()
+}
+
+/// Calls `generate` up to `attempts` times and returns the first path that
+/// strands no tower, otherwise the one stranding the fewest, together with
+/// the stranded towers.
+pub fn find_path_keeping_towers(
+    mut generate: impl FnMut() -> Option<HexPath<GridIndex>>,
+    attempts: u32,
+    towers: &[TowerReach],
+    tile_size: f32,
+) -> Option<(HexPath<GridIndex>, Vec<Entity>)> {
+    let mut best: Option<(HexPath<GridIndex>, Vec<Entity>)> = None;
+    for _ in 0..attempts.max(1) {
+        let Some(path) = generate() else {
+            continue;
+        };
+        let stranded = stranded_towers(&path, towers, tile_size);
+        if best.as_ref().is_none_or(|(_, b)| stranded.len() < b.len()) {
+            let done = stranded.is_empty();
+            best = Some((path, stranded));
+            if done {
+                break;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 10.0;
+
+    fn path(nodes: &[(i32, i32)]) -> HexPath<GridIndex> {
+        let nodes: Vec<GridIndex> = nodes.iter().map(|(q, r)| GridIndex::new(*q, *r)).collect();
+        HexPath {
+            start: nodes[0],
+            end: *nodes.last().unwrap(),
+            nodes,
+        }
+    }
+
+    fn tower(index: u32, q: i32, r: i32) -> TowerReach {
+        TowerReach {
+            entity: Entity::from_raw(index),
+            position: GridIndex::new(q, r).to_world_pos(TILE),
+            range: TILE * 2.0,
+        }
+    }
+
+    #[test]
+    fn towers_out_of_range_are_stranded() {
+        let path = path(&[(0, 0), (1, 0), (2, 0)]);
+        let towers = [tower(1, 1, 1), tower(2, 8, 8)];
+        assert_eq!(
+            stranded_towers(&path, &towers, TILE),
+            vec![Entity::from_raw(2)]
+        );
+    }
+
+    #[test]
+    fn picks_the_path_stranding_the_fewest_towers() {
+        let towers = [tower(1, 0, 5), tower(2, 6, 0)];
+        let mut candidates = vec![
+            Some(path(&[(0, 5), (1, 5)])),
+            None,
+            Some(path(&[(9, 9), (9, 8)])),
+        ];
+        let (chosen, stranded) =
+            find_path_keeping_towers(|| candidates.pop().flatten(), 3, &towers, TILE).unwrap();
+        assert_eq!(chosen.start, GridIndex::new(0, 5));
+        assert_eq!(stranded, vec![Entity::from_raw(2)]);
+
+        let mut calls = 0;
+        let found = find_path_keeping_towers(
+            || {
+                calls += 1;
+                Some(path(&[(0, 5), (6, 0)]))
+            },
+            5,
+            &towers,
+            TILE,
+        );
+        assert!(found.unwrap().1.is_empty());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn no_path_found() {
+        assert!(find_path_keeping_towers(|| None, 3, &[], TILE).is_none());
+    }
+}
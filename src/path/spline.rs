@@ -0,0 +1,192 @@
+use bevy::{ecs::resource::Resource, math::Vec2};
+
+use super::HexPath;
+use crate::grid::GridIndex;
+
+/// World space polyline through the centres of a `HexPath`, parametrised by
+/// the distance travelled from the start.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PathSpline {
+    points: Vec<Vec2>,
+    /// Distance from the start to every point.
+    distances: Vec<f32>,
+    nodes: Vec<GridIndex>,
+    /// Distance from the start to every path node.
+    node_distances: Vec<f32>,
+}
+
+impl PathSpline {
+    /// Straight segments between the hex centres.
+    pub fn polyline(path: &HexPath<GridIndex>, tile_size: f32) -> Self {
+        Self::catmull_rom(path, tile_size, 1)
+    }
+
+    /// Catmull-Rom spline through the hex centres, sampled `samples` times
+    /// per path segment.
+    pub fn catmull_rom(path: &HexPath<GridIndex>, tile_size: f32, samples: usize) -> Self {
+        let samples = samples.max(1);
+        let centres: Vec<Vec2> = path
+            .nodes
+            .iter()
+            .map(|n| n.to_world_pos(tile_size))
+            .collect();
+        let mut points = vec![];
+        let mut node_points = vec![];
+        for i in 0..centres.len() {
+            node_points.push(points.len());
+            points.push(centres[i]);
+            if i + 1 == centres.len() {
+                break;
+            }
+            let p0 = centres[i.saturating_sub(1)];
+            let p1 = centres[i];
+            let p2 = centres[i + 1];
+            let p3 = centres[(i + 2).min(centres.len() - 1)];
+            for s in 1..samples {
+                points.push(catmull_rom_point(p0, p1, p2, p3, s as f32 / samples as f32));
+            }
+        }
+        let mut distances = Vec::with_capacity(points.len());
+        let mut total = 0.0;
+        for (i, p) in points.iter().enumerate() {
+            if i > 0 {
+                total += points[i - 1].distance(*p);
+            }
+            distances.push(total);
+        }
+        let node_distances = node_points.iter().map(|i| distances[*i]).collect();
+        Self {
+            points,
+            distances,
+            nodes: path.nodes.clone(),
+            node_distances,
+        }
+    }
+
+    /// A single straight segment from `from` to the centre of `end`.
+    pub fn straight(from: Vec2, end: GridIndex, tile_size: f32) -> Self {
+        let to = end.to_world_pos(tile_size);
+        let length = from.distance(to);
+        Self {
+            points: vec![from, to],
+            distances: vec![0.0, length],
+            nodes: vec![end],
+            node_distances: vec![length],
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or_default()
+    }
+
+    /// Fraction of the path covered after travelling `distance`.
+    pub fn progress(&self, distance: f32) -> f32 {
+        let length = self.length();
+        if length <= 0.0 {
+            1.0
+        } else {
+            (distance / length).clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn is_finished(&self, distance: f32) -> bool {
+        distance >= self.length()
+    }
+
+    pub fn position_at(&self, distance: f32) -> Vec2 {
+        let Some(last) = self.points.last() else {
+            return Vec2::ZERO;
+        };
+        if distance <= 0.0 {
+            return self.points[0];
+        }
+        let i = self.distances.partition_point(|d| *d <= distance);
+        if i >= self.points.len() {
+            return *last;
+        }
+        let (d0, d1) = (self.distances[i - 1], self.distances[i]);
+        let t = if d1 > d0 {
+            (distance - d0) / (d1 - d0)
+        } else {
+            0.0
+        };
+        self.points[i - 1].lerp(self.points[i], t)
+    }
+
+    /// The first path node strictly ahead of `distance`.
+    pub fn next_node(&self, distance: f32) -> Option<GridIndex> {
+        let i = self.node_distances.partition_point(|d| *d <= distance);
+        self.nodes.get(i).or(self.nodes.last()).copied()
+    }
+}
+
+fn catmull_rom_point(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 10.0;
+
+    fn path(nodes: &[(i32, i32)]) -> HexPath<GridIndex> {
+        let nodes: Vec<GridIndex> = nodes.iter().map(|(q, r)| GridIndex::new(*q, *r)).collect();
+        HexPath {
+            start: nodes[0],
+            end: *nodes.last().unwrap(),
+            nodes,
+        }
+    }
+
+    #[test]
+    fn polyline_follows_hex_centres() {
+        let path = path(&[(0, 0), (1, 0), (2, 0)]);
+        let spline = PathSpline::polyline(&path, TILE);
+        let step = GridIndex::new(0, 0)
+            .to_world_pos(TILE)
+            .distance(GridIndex::new(1, 0).to_world_pos(TILE));
+        assert!((spline.length() - 2.0 * step).abs() < 1e-3);
+        let halfway = spline.position_at(step * 0.5);
+        let expected = GridIndex::new(0, 0)
+            .to_world_pos(TILE)
+            .lerp(GridIndex::new(1, 0).to_world_pos(TILE), 0.5);
+        assert!(halfway.distance(expected) < 1e-3);
+        assert_eq!(spline.next_node(0.0), Some(GridIndex::new(1, 0)));
+        assert_eq!(spline.next_node(step * 1.5), Some(GridIndex::new(2, 0)));
+        assert!(spline.is_finished(spline.length()));
+        assert_eq!(spline.progress(spline.length() * 2.0), 1.0);
+    }
+
+    #[test]
+    fn straight_segments_head_for_the_end() {
+        let end = GridIndex::new(3, 0);
+        let spline = PathSpline::straight(Vec2::ZERO, end, TILE);
+        let to = end.to_world_pos(TILE);
+        assert!((spline.length() - to.length()).abs() < 1e-3);
+        assert!(spline.position_at(spline.length() * 0.5).distance(to * 0.5) < 1e-3);
+        assert_eq!(spline.next_node(0.0), Some(end));
+        assert!(!spline.is_finished(spline.length() - 1.0));
+        assert!(spline.is_finished(spline.length() + 100.0));
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_nodes() {
+        let path = path(&[(0, 0), (1, 0), (1, 1), (1, 2), (2, 2)]);
+        let spline = PathSpline::catmull_rom(&path, TILE, 8);
+        for (node, distance) in path.nodes.iter().zip(&spline.node_distances) {
+            assert!(
+                spline
+                    .position_at(*distance)
+                    .distance(node.to_world_pos(TILE))
+                    < 1e-3
+            );
+        }
+        assert!(spline.length() >= PathSpline::polyline(&path, TILE).length() * 0.9);
+    }
+}
@@ -6,7 +6,11 @@ use bevy::ecs::{
     system::{Commands, Query, Single},
 };
 
-use crate::{assets::PLAYER_INITIAL_GOLD, stats::Health};
+use crate::{
+    assets::{PLAYER_INITIAL_GOLD, PLAYER_INITIAL_HEALTH},
+    economy::LedgerReason,
+    stats::Health,
+};
 
 #[derive(Component)]
 pub struct Player;
@@ -17,6 +21,7 @@ pub struct Gold(pub u32);
 #[derive(Event)]
 pub struct GoldGained {
     pub amount: u32,
+    pub reason: LedgerReason,
 }
 
 pub fn game_running(player: Query<&Health, With<Player>>) -> bool {
@@ -24,7 +29,11 @@ pub fn game_running(player: Query<&Health, With<Player>>) -> bool {
 }
 
 pub fn setup_player(mut commands: Commands) {
-    commands.spawn((Player, Gold(PLAYER_INITIAL_GOLD), Health(10.0)));
+    commands.spawn((
+        Player,
+        Gold(PLAYER_INITIAL_GOLD),
+        Health(PLAYER_INITIAL_HEALTH),
+    ));
 }
 
 pub fn on_gold_gained(trigger: Trigger<GoldGained>, mut query: Single<&mut Gold, With<Player>>) {
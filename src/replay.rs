@@ -0,0 +1,378 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    assets::REPLAY_FILE,
+    command::{PlayerCommand, apply_player_command},
+    difficulty::{Difficulty, StartRun},
+    grid::{MapSize, resize_grid},
+    path::PathMode,
+    results::RestartRun,
+    ron_file::RonFile,
+    save::LoadGame,
+    stats::RunSeed,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationTick>();
+        app.add_observer(record_player_command);
+        app.add_observer(start_recording::<StartRun>);
+        app.add_observer(start_recording::<RestartRun>);
+        app.add_observer(stop_recording);
+        app.add_systems(
+            OnEnter(GameState::MainMenu),
+            start_replay.run_if(resource_exists::<ReplayPlayer>),
+        );
+        app.add_plugins(TickTransitionsPlugin);
+        app.add_systems(
+            FixedFirst,
+            play_replay
+                .run_if(resource_exists::<ReplayPlayer>.and(resource_exists::<ReplayRecording>))
+                .in_set(TickCommands),
+        );
+        app.add_systems(FixedLast, advance_tick);
+        for state in [
+            GameState::BeforeWave,
+            GameState::GameOver,
+            GameState::Victory,
+        ] {
+            app.add_systems(OnEnter(state), write_replay);
+        }
+    }
+}
+
+/// Fixed timesteps simulated since the run started.
+#[derive(Resource, Deref, DerefMut, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationTick(pub u64);
+
+/// A command and the number of ticks simulated before it was given.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedCommand {
+    pub tick: u64,
+    pub command: PlayerCommand,
+}
+
+/// Everything needed to play a run again: how it started and every command
+/// given since.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplayRecording {
+    pub seed: u64,
+    pub map: MapSize,
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub path_mode: PathMode,
+    pub commands: Vec<RecordedCommand>,
+}
+
+impl RonFile for ReplayRecording {
+    const NAME: &'static str = "replay";
+}
+
+/// Recording being played back. Player input is ignored until it runs out.
+#[derive(Resource, Debug, Clone)]
+pub struct ReplayPlayer {
+    pub recording: ReplayRecording,
+    pending: VecDeque<RecordedCommand>,
+}
+
+impl ReplayPlayer {
+    pub fn new(recording: ReplayRecording) -> Self {
+        Self {
+            pending: recording.commands.iter().copied().collect(),
+            recording,
+        }
+    }
+
+    /// Takes the commands given before `tick` was simulated.
+    pub fn due(&mut self, tick: u64) -> Vec<RecordedCommand> {
+        let count = self.pending.iter().take_while(|c| c.tick <= tick).count();
+        self.pending.drain(..count).collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Player commands given at the start of a tick, such as the ones of a replay.
+#[derive(SystemSet, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct TickCommands;
+
+/// Applies state changes at fixed ticks instead of once per frame, so a run
+/// simulates the same way at any frame rate.
+///
+/// `StateTransition` runs twice at the start of each tick. The run before
+/// `TickCommands` applies the changes queued by the previous tick, so the
+/// commands see the state they were recorded in. The run after applies the
+/// changes the commands queue, so the tick they were given in already
+/// simulates the new state, as it did when they were recorded.
+pub struct TickTransitionsPlugin;
+
+impl Plugin for TickTransitionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedFirst,
+            (
+                apply_state_transitions.before(TickCommands),
+                apply_state_transitions.after(TickCommands),
+            ),
+        );
+    }
+}
+
+pub fn apply_state_transitions(world: &mut World) {
+    world.run_schedule(StateTransition);
+}
+
+pub fn advance_tick(mut tick: ResMut<SimulationTick>) {
+    tick.0 += 1;
+}
+
+pub fn start_recording<E: Event>(
+    _trigger: Trigger<E>,
+    mut commands: Commands,
+    mut tick: ResMut<SimulationTick>,
+    seed: Res<RunSeed>,
+    map: Res<MapSize>,
+    difficulty: Res<Difficulty>,
+    path_mode: Res<PathMode>,
+) {
+    tick.0 = 0;
+    commands.insert_resource(ReplayRecording {
+        seed: seed.0,
+        map: *map,
+        difficulty: *difficulty,
+        path_mode: *path_mode,
+        commands: vec![],
+    });
+}
+
+/// A resumed run did not start from its seed and cannot be replayed.
+pub fn stop_recording(_trigger: Trigger<LoadGame>, mut commands: Commands) {
+    commands.remove_resource::<ReplayRecording>();
+}
+
+pub fn record_player_command(
+    trigger: Trigger<PlayerCommand>,
+    mut commands: Commands,
+    tick: Res<SimulationTick>,
+    recording: Option<ResMut<ReplayRecording>>,
+    replay: Option<Res<ReplayPlayer>>,
+) {
+    if replay.is_some() {
+        return;
+    }
+    let command = *trigger.event();
+    if let Some(mut recording) = recording {
+        recording.commands.push(RecordedCommand {
+            tick: tick.0,
+            command,
+        });
+    }
+    commands.run_system_cached_with(apply_player_command, command);
+}
+
+pub fn play_replay(
+    mut commands: Commands,
+    tick: Res<SimulationTick>,
+    mut replay: ResMut<ReplayPlayer>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    let due = replay.due(tick.0);
+    recording.commands.extend(&due);
+    for recorded in due {
+        commands.run_system_cached_with(apply_player_command, recorded.command);
+    }
+    if replay.is_finished() {
+        info!("replay finished");
+        commands.remove_resource::<ReplayPlayer>();
+    }
+}
+
+/// Starts the run of the replay with its seed, map, difficulty and path mode.
+pub fn start_replay(
+    mut commands: Commands,
+    replay: Res<ReplayPlayer>,
+    mut seed: ResMut<RunSeed>,
+    mut map: ResMut<MapSize>,
+    mut difficulty: ResMut<Difficulty>,
+    mut path_mode: ResMut<PathMode>,
+) {
+    let recording = &replay.recording;
+    info!("replaying {} commands", recording.commands.len());
+    *seed = RunSeed(recording.seed);
+    *map = recording.map;
+    *difficulty = recording.difficulty;
+    *path_mode = recording.path_mode;
+    commands.run_system_cached(resize_grid);
+    commands.trigger(StartRun);
+}
+
+pub fn write_replay(recording: Option<Res<ReplayRecording>>) {
+    let Some(recording) = recording else {
+        return;
+    };
+    if let Err(e) = recording.save(REPLAY_FILE) {
+        error!("{e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        grid::{GridEntry, GridIndex, HexHashGrid},
+        headless::simulation_app,
+        path::HexPath,
+        player::{Gold, Player},
+        results::RunStats,
+        stats::Wave,
+        tower::{TowerIndex, TowerKind},
+    };
+
+    fn recording() -> ReplayRecording {
+        let place = |tick, q| RecordedCommand {
+            tick,
+            command: PlayerCommand::PlaceTower {
+                index: GridIndex { q, r: 0 },
+                kind: TowerKind::Frost,
+            },
+        };
+        ReplayRecording {
+            seed: 99,
+            map: MapSize::Small,
+            difficulty: Difficulty::Easy,
+            path_mode: PathMode::EveryNWaves(3),
+            commands: vec![
+                place(0, 1),
+                place(0, 2),
+                RecordedCommand {
+                    tick: 40,
+                    command: PlayerCommand::StartWave,
+                },
+                RecordedCommand {
+                    tick: 300,
+                    command: PlayerCommand::SellTower {
+                        index: GridIndex { q: 1, r: 0 },
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn commands_come_due_in_tick_order() {
+        let mut player = ReplayPlayer::new(recording());
+        assert_eq!(player.due(0).len(), 2);
+        assert!(player.due(39).is_empty());
+        let due = player.due(120);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].command, PlayerCommand::StartWave);
+        assert!(!player.is_finished());
+        assert_eq!(player.due(u64::MAX).len(), 1);
+        assert!(player.is_finished());
+    }
+
+    fn state(world: &World) -> GameState {
+        *world.resource::<State<GameState>>().get()
+    }
+
+    /// A simulation recording its runs, updated until its assets have loaded.
+    fn loaded_app(replay: Option<ReplayRecording>) -> App {
+        let mut app = simulation_app();
+        app.add_plugins(ReplayPlugin);
+        if let Some(recording) = replay {
+            app.insert_resource(ReplayPlayer::new(recording));
+        }
+        app.finish();
+        app.cleanup();
+        let started = Instant::now();
+        while matches!(state(app.world()), GameState::Startup | GameState::Loading) {
+            assert!(
+                started.elapsed().as_secs() < 30,
+                "assets did not finish loading"
+            );
+            app.update();
+        }
+        app
+    }
+
+    fn run_until(app: &mut App, condition: impl Fn(&World) -> bool) {
+        for _ in 0..64 * 300 {
+            if condition(app.world()) {
+                return;
+            }
+            app.update();
+        }
+        panic!("condition not reached");
+    }
+
+    fn in_build_phase(world: &World) -> bool {
+        state(world) == GameState::BeforeWave && world.contains_resource::<HexPath<GridIndex>>()
+    }
+
+    fn after_first_wave(world: &World) -> bool {
+        world.resource::<Wave>().0 == 1
+            && !matches!(state(world), GameState::Wave | GameState::AfterWave)
+    }
+
+    fn outcome(app: &mut App) -> (RunStats, u32, Vec<GridIndex>) {
+        let world = app.world_mut();
+        let gold = world
+            .query_filtered::<&Gold, With<Player>>()
+            .single(world)
+            .unwrap()
+            .0;
+        let mut towers: Vec<GridIndex> = world
+            .query::<&TowerIndex>()
+            .iter(world)
+            .map(|i| i.0)
+            .collect();
+        towers.sort_by_key(|i| (i.q, i.r));
+        (*world.resource::<RunStats>(), gold, towers)
+    }
+
+    #[test]
+    fn a_replay_plays_the_run_again() {
+        let mut recorded = loaded_app(None);
+        let world = recorded.world_mut();
+        world.insert_resource(RunSeed(3));
+        world.insert_resource(MapSize::Small);
+        world.run_system_cached(resize_grid).unwrap();
+        world.trigger(StartRun);
+        run_until(&mut recorded, in_build_phase);
+        let world = recorded.world_mut();
+        let path = world.resource::<HexPath<GridIndex>>().nodes.clone();
+        let grid = world.resource::<HexHashGrid>();
+        let mut sites: Vec<GridIndex> = vec![];
+        for index in path.iter().flat_map(|n| n.neighbours()) {
+            if grid.contains(&index) && grid[index] == GridEntry::None && !sites.contains(&index) {
+                sites.push(index);
+            }
+        }
+        for index in sites.into_iter().take(3) {
+            recorded.world_mut().trigger(PlayerCommand::PlaceTower {
+                index,
+                kind: TowerKind::Basic,
+            });
+            recorded.update();
+        }
+        recorded.world_mut().trigger(PlayerCommand::StartWave);
+        run_until(&mut recorded, after_first_wave);
+        let expected = outcome(&mut recorded);
+        assert!(!expected.2.is_empty());
+
+        let recording = recorded.world().resource::<ReplayRecording>().clone();
+        let mut replayed = loaded_app(Some(recording));
+        run_until(&mut replayed, after_first_wave);
+        assert_eq!(outcome(&mut replayed), expected);
+    }
+}
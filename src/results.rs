@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    assets::FONT_SIZE,
+    cleanup_path,
+    difficulty::{Difficulty, reset_player},
+    economy::{Ledger, LedgerReason},
+    enemy::{Enemy, EnemyKilled, EnemyLeaked},
+    grid::HexHashGrid,
+    menu::ReturnToMainMenu,
+    player::{Gold, GoldGained, Player},
+    remove_second_path,
+    score::{FinalScore, HighScores},
+    speed::{GAME_SPEEDS, SpeedControl},
+    stats::{Health, Wave},
+    tower::{Projectile, SelectedTower, Tower},
+    ui::UiFont,
+};
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>();
+        app.add_observer(count_gold_earned);
+        app.add_observer(count_enemies_killed);
+        app.add_observer(count_enemies_leaked);
+        app.add_observer(abandon_run);
+        app.add_observer(restart_run);
+    }
+}
+
+/// Results screen shown when a run ends.
+pub struct ResultsUiPlugin;
+
+impl Plugin for ResultsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameOver), spawn_results_screen);
+        app.add_systems(OnEnter(GameState::Victory), spawn_results_screen);
+        app.add_systems(
+            Update,
+            restart_on_key.run_if(in_state(GameState::GameOver).or(in_state(GameState::Victory))),
+        );
+    }
+}
+
+/// Totals of the current run, shown on the results screen.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct RunStats {
+    pub waves_survived: u32,
+    pub gold_earned: u32,
+    pub enemies_killed: u32,
+    /// Enemies that reached the end of the path.
+    #[serde(default)]
+    pub enemies_leaked: u32,
+    /// Gold dropped by killed enemies.
+    pub kill_value: u32,
+    /// Game time spent building and fighting, excluding pauses.
+    pub play_seconds: f32,
+    /// See `HighScoreEntry::map_hash`.
+    pub map_hash: u64,
+}
+
+/// High-score entries listed on the results screen.
+static HIGH_SCORES_SHOWN: usize = 5;
+
+/// Resets the board and the player and starts again from the first wave.
+#[derive(Event)]
+pub struct RestartRun;
+
+/// Clears the board and the totals of the current run.
+#[derive(Event)]
+pub struct AbandonRun;
+
+#[derive(Component)]
+pub struct RestartButton;
+
+pub fn count_gold_earned(trigger: Trigger<GoldGained>, mut stats: ResMut<RunStats>) {
+    let event = trigger.event();
+    if event.reason != LedgerReason::Sale {
+        stats.gold_earned += event.amount;
+    }
+    if event.reason == LedgerReason::Kill {
+        stats.kill_value += event.amount;
+    }
+}
+
+pub fn count_enemies_killed(_trigger: Trigger<EnemyKilled>, mut stats: ResMut<RunStats>) {
+    stats.enemies_killed += 1;
+}
+
+pub fn count_enemies_leaked(_trigger: Trigger<EnemyLeaked>, mut stats: ResMut<RunStats>) {
+    stats.enemies_leaked += 1;
+}
+
+pub fn spawn_results_screen(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    stats: Res<RunStats>,
+    font: Res<UiFont>,
+    final_score: Option<Res<FinalScore>>,
+    high_scores: Option<Res<HighScores>>,
+) {
+    let title = if *state.get() == GameState::Victory {
+        "Victory"
+    } else {
+        "Game over"
+    };
+    let text_font = TextFont::default()
+        .with_font(font.0.clone())
+        .with_font_size(FONT_SIZE);
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..Default::default()
+            },
+            BackgroundColor(Color::hsla(0.0, 0.0, 0.0, 0.7)),
+            StateScoped(*state.get()),
+        ))
+        .with_children(|builder| {
+            builder.spawn((
+                Text::new(title),
+                text_font.clone().with_font_size(FONT_SIZE * 2.0),
+            ));
+            for line in [
+                format!("waves survived: {}", stats.waves_survived),
+                format!("gold earned: {}", stats.gold_earned),
+                format!("enemies killed: {}", stats.enemies_killed),
+            ] {
+                builder.spawn((Text::new(line), text_font.clone()));
+            }
+            if let Some(final_score) = &final_score {
+                let score = final_score.score;
+                builder.spawn((
+                    Text::new(format!("score: {}", score.total())),
+                    text_font.clone().with_font_size(FONT_SIZE * 1.5),
+                ));
+                builder.spawn((
+                    Text::new(format!(
+                        "kills {} + waves {} + lives {} + time {}",
+                        score.kills, score.waves, score.lives, score.time_bonus
+                    )),
+                    text_font.clone(),
+                ));
+                let table = high_scores
+                    .as_ref()
+                    .map(|h| h.table(&final_score.key))
+                    .unwrap_or_default();
+                builder.spawn((
+                    Text::new(format!(
+                        "high scores {} {}",
+                        final_score.key.map, final_score.key.difficulty
+                    )),
+                    text_font.clone(),
+                ));
+                for (rank, entry) in table.iter().take(HIGH_SCORES_SHOWN).enumerate() {
+                    let marker = if final_score.rank == Some(rank) {
+                        ">"
+                    } else {
+                        " "
+                    };
+                    builder.spawn((
+                        Text::new(format!(
+                            "{marker}{:>2}. {:>6}  wave {:<3} map {:08x}",
+                            rank + 1,
+                            entry.score.total(),
+                            entry.waves,
+                            entry.map_hash as u32
+                        )),
+                        text_font.clone(),
+                    ));
+                }
+            }
+            builder
+                .spawn((
+                    Node {
+                        margin: UiRect::top(Val::Px(12.0)),
+                        padding: UiRect::axes(Val::Px(16.0), Val::Px(6.0)),
+                        ..Default::default()
+                    },
+                    Button,
+                    BackgroundColor(Color::hsla(0.0, 0.0, 0.25, 1.0)),
+                    RestartButton,
+                ))
+                .with_child((Text::new("Restart [R]"), text_font.clone()))
+                .observe(|_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(RestartRun);
+                });
+            builder
+                .spawn((
+                    Node {
+                        padding: UiRect::axes(Val::Px(16.0), Val::Px(6.0)),
+                        ..Default::default()
+                    },
+                    Button,
+                    BackgroundColor(Color::hsla(0.0, 0.0, 0.25, 1.0)),
+                ))
+                .with_child((Text::new("Main menu [M]"), text_font.clone()))
+                .observe(|_: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.trigger(ReturnToMainMenu);
+                });
+        });
+}
+
+pub fn restart_on_key(mut commands: Commands, keys: Res<ButtonInput<KeyCode>>) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        commands.trigger(RestartRun);
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        commands.trigger(ReturnToMainMenu);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn abandon_run(
+    _trigger: Trigger<AbandonRun>,
+    mut commands: Commands,
+    entities: Query<Entity, Or<(With<Tower>, With<Enemy>, With<Projectile>)>>,
+    mut grid: ResMut<HexHashGrid>,
+    mut wave: ResMut<Wave>,
+    mut stats: ResMut<RunStats>,
+    mut ledger: ResMut<Ledger>,
+    mut selected: ResMut<SelectedTower>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    info!("clearing run");
+    SpeedControl::Speed(GAME_SPEEDS[0]).apply(&mut time);
+    for entity in &entities {
+        commands.entity(entity).despawn();
+    }
+    grid.clear();
+    commands.run_system_cached(remove_second_path);
+    commands.run_system_cached(cleanup_path);
+    wave.0 = 0;
+    *stats = RunStats::default();
+    *ledger = Ledger::default();
+    selected.0 = None;
+}
+
+pub fn restart_run(
+    _trigger: Trigger<RestartRun>,
+    mut commands: Commands,
+    mut player: Query<(&mut Gold, &mut Health), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    difficulty: Res<Difficulty>,
+) {
+    info!("restarting run");
+    commands.trigger(AbandonRun);
+    reset_player(&difficulty, &mut player);
+    next_state.set(GameState::BeforeWave);
+}
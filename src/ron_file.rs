@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RonFileError {
+    #[error("could not access {0}: {1}")]
+    Io(&'static str, #[source] std::io::Error),
+    #[error("could not parse {0}: {1}")]
+    Parse(&'static str, #[source] ron::error::SpannedError),
+    #[error("could not write {0}: {1}")]
+    Write(&'static str, #[source] ron::Error),
+    #[error("{0} version {1} is not supported")]
+    Version(&'static str, u32),
+}
+
+/// A value stored on its own in a RON file.
+pub trait RonFile: Serialize + DeserializeOwned {
+    /// What the file holds, for error messages.
+    const NAME: &'static str;
+
+    fn load(path: impl AsRef<Path>) -> Result<Self, RonFileError> {
+        let text = std::fs::read_to_string(path).map_err(|e| RonFileError::Io(Self::NAME, e))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, RonFileError> {
+        ron::de::from_str(text).map_err(|e| RonFileError::Parse(Self::NAME, e))
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> Result<(), RonFileError> {
+        let text =
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default().struct_names(true))
+                .map_err(|e| RonFileError::Write(Self::NAME, e))?;
+        std::fs::write(path, text).map_err(|e| RonFileError::Io(Self::NAME, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Notes {
+        title: String,
+        lines: Vec<(u32, Option<String>)>,
+    }
+
+    impl RonFile for Notes {
+        const NAME: &'static str = "notes";
+    }
+
+    #[test]
+    fn values_round_trip_through_a_file() {
+        let notes = Notes {
+            title: "wave 3".to_string(),
+            lines: vec![(1, Some("leak".to_string())), (2, None)],
+        };
+        let path = std::env::temp_dir().join(format!("random_td_notes_{}.ron", std::process::id()));
+        notes.save(&path).unwrap();
+        let loaded = Notes::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, notes);
+        assert!(matches!(
+            Notes::load(&path),
+            Err(RonFileError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+}
@@ -0,0 +1,430 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    assets::SAVE_FILE,
+    difficulty::Difficulty,
+    economy::Ledger,
+    generate_path,
+    grid::{
+        GridEntity, GridEntry, GridIndex, HexGridRenderRadius, HexHashGrid, MapSize, resize_grid,
+    },
+    path::{HexPath, PathMode, regeneration::FreeRelocation},
+    place_path,
+    player::{Gold, Player},
+    results::{AbandonRun, RunStats},
+    ron_file::{RonFile, RonFileError},
+    stats::{Health, RunRng, RunSeed, TowerBaseStats, Wave},
+    tower::{BaseTowerImage, TargetFilter, Tower, TowerIndex, TowerKind, spawn_tower},
+    ui::UiMessage,
+    veterancy::{TowerRank, TowerRecord},
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(load_game);
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            capture_save.pipe(write_save).after(generate_path),
+        );
+        app.add_systems(
+            Update,
+            restore_save
+                .after(resize_grid)
+                .run_if(resource_exists::<PendingSave>),
+        );
+        for state in [GameState::GameOver, GameState::Victory] {
+            app.add_systems(OnEnter(state), delete_save);
+        }
+    }
+}
+
+/// Format of `SaveGame`. Bump it when the format changes and teach `migrate`
+/// to read the previous one.
+pub static SAVE_VERSION: u32 = 2;
+
+/// A run as it was at the start of a build phase.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaveGame {
+    pub version: u32,
+    pub map: MapSize,
+    pub difficulty: Difficulty,
+    /// Missing from version 1, which only had the default mode.
+    #[serde(default)]
+    pub path_mode: PathMode,
+    pub seed: u64,
+    pub wave: u32,
+    pub gold: u32,
+    pub health: f32,
+    pub path: HexPath<GridIndex>,
+    pub towers: Vec<SavedTower>,
+    pub ledger: Ledger,
+    pub stats: RunStats,
+}
+
+/// A tower and the stats it was built with. Aura towers have no rolled stats.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SavedTower {
+    pub index: GridIndex,
+    pub kind: TowerKind,
+    pub base: Option<TowerBaseStats>,
+    pub filter: Option<TargetFilter>,
+    pub record: Option<TowerRecord>,
+    pub rank: Option<TowerRank>,
+    pub free_relocation: bool,
+}
+
+/// The part of every save format that never changes.
+#[derive(Deserialize)]
+#[serde(rename = "SaveGame")]
+struct SaveHeader {
+    version: u32,
+}
+
+impl RonFile for SaveGame {
+    const NAME: &'static str = "save game";
+
+    fn parse(text: &str) -> Result<Self, RonFileError> {
+        let header: SaveHeader =
+            ron::de::from_str(text).map_err(|e| RonFileError::Parse(Self::NAME, e))?;
+        migrate(header.version, text)
+    }
+}
+
+/// Reads a save written in format `version`, converting older formats to the
+/// current one.
+pub fn migrate(version: u32, text: &str) -> Result<SaveGame, RonFileError> {
+    let parse = |text| {
+        ron::de::from_str::<SaveGame>(text).map_err(|e| RonFileError::Parse(SaveGame::NAME, e))
+    };
+    match version {
+        1 => Ok(SaveGame {
+            version: SAVE_VERSION,
+            ..parse(text)?
+        }),
+        v if v == SAVE_VERSION => parse(text),
+        v => Err(RonFileError::Version(SaveGame::NAME, v)),
+    }
+}
+
+/// Resumes the run stored in `SAVE_FILE`.
+#[derive(Event)]
+pub struct LoadGame;
+
+/// Save game waiting for the board to be rebuilt before it is restored.
+#[derive(Resource, Deref)]
+pub struct PendingSave(pub SaveGame);
+
+pub fn has_save() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn capture_save(
+    map: Res<MapSize>,
+    difficulty: Res<Difficulty>,
+    path_mode: Res<PathMode>,
+    seed: Res<RunSeed>,
+    wave: Res<Wave>,
+    player: Single<(&Gold, &Health), With<Player>>,
+    path: Option<Res<HexPath<GridIndex>>>,
+    towers: Query<
+        (
+            &TowerIndex,
+            &TowerKind,
+            Option<&TowerBaseStats>,
+            Option<&TargetFilter>,
+            Option<&TowerRecord>,
+            Option<&TowerRank>,
+            Has<FreeRelocation>,
+        ),
+        With<Tower>,
+    >,
+    ledger: Res<Ledger>,
+    stats: Res<RunStats>,
+) -> Option<SaveGame> {
+    let (gold, health) = *player;
+    let mut towers: Vec<SavedTower> = towers
+        .iter()
+        .map(
+            |(index, kind, base, filter, record, rank, free_relocation)| SavedTower {
+                index: index.0,
+                kind: *kind,
+                base: base.copied(),
+                filter: filter.copied(),
+                record: record.copied(),
+                rank: rank.copied(),
+                free_relocation,
+            },
+        )
+        .collect();
+    towers.sort_by_key(|t| (t.index.q, t.index.r));
+    Some(SaveGame {
+        version: SAVE_VERSION,
+        map: *map,
+        difficulty: *difficulty,
+        path_mode: *path_mode,
+        seed: seed.0,
+        wave: wave.0,
+        gold: gold.0,
+        health: health.0,
+        path: path?.clone(),
+        towers,
+        ledger: ledger.clone(),
+        stats: *stats,
+    })
+}
+
+pub fn write_save(In(save): In<Option<SaveGame>>) {
+    let Some(save) = save else {
+        return;
+    };
+    match save.save(SAVE_FILE) {
+        Ok(()) => info!("saved wave {}", save.wave),
+        Err(e) => error!("{e}"),
+    }
+}
+
+pub fn delete_save() {
+    if let Err(e) = std::fs::remove_file(SAVE_FILE)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("could not delete save game: {e}");
+    }
+}
+
+pub fn load_game(_trigger: Trigger<LoadGame>, mut commands: Commands) {
+    let save = match SaveGame::load(SAVE_FILE) {
+        Ok(save) => save,
+        Err(e) => {
+            error!("{e}");
+            commands.trigger(UiMessage(format!("Failed to load the saved game: {e}")));
+            return;
+        }
+    };
+    info!("loading wave {}", save.wave);
+    commands.trigger(AbandonRun);
+    resume(&mut commands, save);
+}
+
+/// Sets up the saved run's settings and queues the save for [`restore_save`].
+fn resume(commands: &mut Commands, save: SaveGame) {
+    commands.insert_resource(save.map);
+    commands.insert_resource(save.difficulty);
+    commands.insert_resource(save.path_mode);
+    commands.insert_resource(RunSeed(save.seed));
+    commands.insert_resource(PendingSave(save));
+}
+
+/// Rebuilds the saved run on the current board and starts its build phase.
+#[allow(clippy::too_many_arguments)]
+pub fn restore_save(
+    mut commands: Commands,
+    pending: Res<PendingSave>,
+    mut grid: ResMut<HexHashGrid>,
+    tiles: Query<(Entity, &GridEntity)>,
+    mut player: Query<(&mut Gold, &mut Health), With<Player>>,
+    mut wave: ResMut<Wave>,
+    base_tower_image: Res<BaseTowerImage>,
+    render_radius: Res<HexGridRenderRadius>,
+    mut rng: ResMut<RunRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let save = &pending.0;
+    wave.0 = save.wave;
+    for (mut gold, mut health) in &mut player {
+        gold.0 = save.gold;
+        health.0 = save.health;
+    }
+    commands.insert_resource(save.ledger.clone());
+    commands.insert_resource(save.stats);
+    commands.run_system_cached_with(place_path, save.path.clone());
+    for saved in &save.towers {
+        let Some((tile, _)) = tiles.iter().find(|(_, tile)| tile.0 == saved.index) else {
+            warn!("saved tower at {:?} is outside of the board", saved.index);
+            continue;
+        };
+        let tower = spawn_tower(
+            tile,
+            saved.index,
+            saved.kind,
+            &mut commands,
+            &base_tower_image,
+            **render_radius,
+            &mut rng.0,
+        );
+        let mut tower = commands.entity(tower);
+        if let Some(base) = saved.base {
+            tower.insert(base);
+        }
+        if let Some(filter) = saved.filter {
+            tower.insert(filter);
+        }
+        if let Some(record) = saved.record {
+            tower.insert(record);
+        }
+        if let Some(rank) = saved.rank {
+            tower.insert(rank);
+        }
+        if saved.free_relocation {
+            tower.insert(FreeRelocation);
+        }
+        grid[saved.index] = GridEntry::Tower;
+    }
+    commands.remove_resource::<PendingSave>();
+    next_state.set(GameState::BeforeWave);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{MinimalPlugins, state::app::StatesPlugin};
+
+    use super::*;
+    use crate::{grid::GridPlugin, path::spline::PathSpline};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()));
+        app.init_asset::<Mesh>();
+        app.init_asset::<ColorMaterial>();
+        app.insert_state(GameState::MainMenu);
+        app.add_plugins(GridPlugin::default());
+        app.init_resource::<Difficulty>();
+        app.init_resource::<PathMode>();
+        app.init_resource::<RunSeed>();
+        app.init_resource::<RunRng>();
+        app.init_resource::<Ledger>();
+        app.init_resource::<RunStats>();
+        app.insert_resource(Wave(0));
+        app.insert_resource(BaseTowerImage(Handle::default()));
+        app.add_systems(Update, restore_save.run_if(resource_exists::<PendingSave>));
+        app.world_mut().spawn((Player, Gold(0), Health(0.0)));
+        app
+    }
+
+    fn tower(q: i32, r: i32, kind: TowerKind) -> SavedTower {
+        SavedTower {
+            index: GridIndex { q, r },
+            kind,
+            base: None,
+            filter: None,
+            record: None,
+            rank: None,
+            free_relocation: false,
+        }
+    }
+
+    fn save() -> SaveGame {
+        let path = HexPath {
+            nodes: vec![
+                GridIndex { q: 0, r: 0 },
+                GridIndex { q: 1, r: 0 },
+                GridIndex { q: 2, r: 0 },
+            ],
+            start: GridIndex { q: 0, r: 0 },
+            end: GridIndex { q: 2, r: 0 },
+        };
+        SaveGame {
+            version: SAVE_VERSION,
+            map: MapSize::Medium,
+            difficulty: Difficulty::Hard,
+            path_mode: PathMode::EveryWave,
+            seed: 42,
+            wave: 3,
+            gold: 77,
+            health: 4.0,
+            path,
+            towers: vec![
+                SavedTower {
+                    base: Some(TowerBaseStats {
+                        damage: 9.0,
+                        range: 200.0,
+                        fire_rate: 60.0,
+                    }),
+                    filter: Some(TargetFilter::Ground),
+                    record: Some(TowerRecord {
+                        xp: 12.0,
+                        damage_dealt: 300.0,
+                        kills: 5,
+                    }),
+                    rank: Some(TowerRank(1)),
+                    free_relocation: true,
+                    ..tower(0, 1, TowerKind::Basic)
+                },
+                tower(1, 1, TowerKind::Frost),
+            ],
+            ledger: Ledger {
+                income: 120,
+                expenses: 60,
+                ..Default::default()
+            },
+            stats: RunStats {
+                waves_survived: 3,
+                gold_earned: 120,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Tile contents of the board, sorted for comparison.
+    fn board(world: &World) -> Vec<(GridIndex, bool, bool)> {
+        let grid = world.resource::<HexHashGrid>();
+        let mut board: Vec<_> = grid
+            .keys()
+            .map(|i| (i, grid[i] == GridEntry::Tower, grid[i] == GridEntry::None))
+            .collect();
+        board.sort_by_key(|(i, ..)| (i.q, i.r));
+        board
+    }
+
+    #[test]
+    fn saves_round_trip_through_the_world() {
+        let mut restored = app();
+        let saved = save();
+        resume(&mut restored.world_mut().commands(), saved.clone());
+        restored.update();
+        restored.update();
+
+        let world = restored.world_mut();
+        assert!(world.get_resource::<PendingSave>().is_none());
+        assert!(world.contains_resource::<PathSpline>());
+        assert_eq!(world.resource::<Wave>().0, 3);
+        let captured = world.run_system_cached(capture_save).unwrap();
+        assert_eq!(captured.as_ref(), Some(&saved));
+
+        let mut other = app();
+        resume(&mut other.world_mut().commands(), captured.unwrap());
+        other.update();
+        other.update();
+        assert_eq!(board(restored.world()), board(other.world()));
+        let recaptured = other.world_mut().run_system_cached(capture_save).unwrap();
+        assert_eq!(recaptured, Some(saved));
+    }
+
+    #[test]
+    fn version_1_saves_use_the_default_path_mode() {
+        let saved = save();
+        let text = ron::ser::to_string(&saved)
+            .unwrap()
+            .replace(&format!("version:{SAVE_VERSION}"), "version:1")
+            .replace("path_mode:EveryWave,", "");
+        assert!(!text.contains("path_mode"));
+        assert_eq!(SaveGame::parse(&text).unwrap(), saved);
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        let mut saved = save();
+        saved.version = SAVE_VERSION + 1;
+        let text = ron::ser::to_string(&saved).unwrap();
+        assert!(matches!(
+            SaveGame::parse(&text),
+            Err(RonFileError::Version(_, v)) if v == SAVE_VERSION + 1
+        ));
+    }
+}
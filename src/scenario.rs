@@ -0,0 +1,293 @@
+//! Harness for gameplay tests: runs the `SimulationPlugin` without a GPU on a
+//! scripted set of waves, so a scenario reads as a few chained calls.
+
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    command::{PlayerCommand, apply_player_command},
+    difficulty::StartRun,
+    grid::{GridEntry, GridIndex, HexHashGrid, MapSize, resize_grid},
+    headless::simulation_app,
+    path::HexPath,
+    player::{Gold, Player},
+    results::RunStats,
+    stats::{Health, RunSeed, Wave},
+    tower::{Tower, TowerKind},
+    wave::{WaveDefinition, WaveGroup, WaveScript, WaveScriptHandle},
+};
+
+const SEED: u64 = 7;
+/// Simulated ticks a scenario may take to reach a condition, about five
+/// minutes of game time.
+const MAX_TICKS: u32 = 64 * 300;
+
+/// A wave of `count` enemies of the archetype named `enemy`.
+pub fn wave(enemy: &str, count: u32) -> WaveDefinition {
+    WaveDefinition {
+        groups: vec![WaveGroup::new(enemy, count)],
+        second_path: false,
+    }
+}
+
+pub struct Scenario {
+    pub app: App,
+}
+
+impl Scenario {
+    /// A run on a small map, in the build phase before the first of `waves`.
+    pub fn new(waves: Vec<WaveDefinition>) -> Self {
+        let mut app = simulation_app();
+        app.finish();
+        app.cleanup();
+        let started = Instant::now();
+        while state(app.world()) != GameState::MainMenu {
+            assert!(
+                started.elapsed().as_secs() < 30,
+                "assets did not finish loading"
+            );
+            app.update();
+        }
+        let world = app.world_mut();
+        let handle = world.resource::<WaveScriptHandle>().0.clone();
+        world
+            .resource_mut::<Assets<WaveScript>>()
+            .get_mut(&handle)
+            .expect("wave script is loaded")
+            .waves = waves;
+        world.insert_resource(RunSeed(SEED));
+        world.insert_resource(MapSize::Small);
+        world.run_system_cached(resize_grid).unwrap();
+        world.trigger(StartRun);
+        let mut scenario = Self { app };
+        scenario.run_until(|world| {
+            state(world) == GameState::BeforeWave && world.contains_resource::<HexPath<GridIndex>>()
+        });
+        scenario
+    }
+
+    /// A single wave of `count` enemies of the archetype named `enemy`.
+    pub fn with_enemies(enemy: &str, count: u32) -> Self {
+        Self::new(vec![wave(enemy, count)])
+    }
+
+    pub fn command(&mut self, command: PlayerCommand) -> &mut Self {
+        self.app
+            .world_mut()
+            .run_system_cached_with(apply_player_command, command)
+            .unwrap();
+        self
+    }
+
+    /// Builds `count` towers on the free tiles next to the path, closest to
+    /// its start first.
+    pub fn towers_along_path(&mut self, kind: TowerKind, count: usize) -> &mut Self {
+        let world = self.app.world();
+        let path = world.resource::<HexPath<GridIndex>>();
+        let grid = world.resource::<HexHashGrid>();
+        let mut sites: Vec<GridIndex> = vec![];
+        for index in path.nodes.iter().flat_map(|n| n.neighbours()) {
+            if grid.contains(&index) && grid[index] == GridEntry::None && !sites.contains(&index) {
+                sites.push(index);
+            }
+        }
+        assert!(sites.len() >= count, "not enough free tiles along the path");
+        let built = self.towers();
+        for index in sites.into_iter().take(count) {
+            self.command(PlayerCommand::PlaceTower { index, kind });
+        }
+        assert_eq!(self.towers(), built + count, "not enough gold");
+        self
+    }
+
+    pub fn start_wave(&mut self) -> &mut Self {
+        self.command(PlayerCommand::StartWave);
+        self.run_until(|world| state(world) == GameState::Wave)
+    }
+
+    /// Starts the next wave and plays it until the run moves on.
+    pub fn run_wave(&mut self) -> &mut Self {
+        self.start_wave();
+        self.run_until(|world| !matches!(state(world), GameState::Wave | GameState::AfterWave))
+    }
+
+    /// Simulates `ticks` fixed timesteps.
+    pub fn step(&mut self, ticks: u32) -> &mut Self {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+        self
+    }
+
+    /// Simulates until `condition` holds, failing the test if it never does.
+    pub fn run_until(&mut self, condition: impl Fn(&World) -> bool) -> &mut Self {
+        for _ in 0..MAX_TICKS {
+            if condition(self.app.world()) {
+                return self;
+            }
+            self.app.update();
+        }
+        panic!("condition not reached within {MAX_TICKS} ticks");
+    }
+
+    pub fn state(&self) -> GameState {
+        state(self.app.world())
+    }
+
+    pub fn wave(&self) -> u32 {
+        self.app.world().resource::<Wave>().0
+    }
+
+    pub fn stats(&self) -> RunStats {
+        *self.app.world().resource::<RunStats>()
+    }
+
+    pub fn gold(&mut self) -> u32 {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<&Gold, With<Player>>()
+            .single(world)
+            .unwrap()
+            .0
+    }
+
+    pub fn health(&mut self) -> f32 {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<&Health, With<Player>>()
+            .single(world)
+            .unwrap()
+            .0
+    }
+
+    pub fn towers(&mut self) -> usize {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<(), With<Tower>>()
+            .iter(world)
+            .count()
+    }
+}
+
+fn state(world: &World) -> GameState {
+    *world.resource::<State<GameState>>().get()
+}
+
+mod tests {
+    use super::*;
+    use crate::{
+        assets::{BUILD_PHASE_SECONDS, PLAYER_INITIAL_HEALTH},
+        enemy::{OnSecondPath, PathProgress},
+        grid::PathStart,
+        path::SecondPath,
+        wave::SpawnPoint,
+    };
+
+    fn path_starts(world: &mut World) -> usize {
+        world
+            .query_filtered::<(), With<PathStart>>()
+            .iter(world)
+            .count()
+    }
+
+    #[test]
+    fn towers_kill_an_enemy_before_it_reaches_the_end() {
+        let mut scenario = Scenario::with_enemies("Grunt", 1);
+        scenario.towers_along_path(TowerKind::Basic, 4).run_wave();
+        assert_eq!(scenario.stats().enemies_killed, 1);
+        assert_eq!(scenario.stats().enemies_leaked, 0);
+        assert_eq!(scenario.health(), PLAYER_INITIAL_HEALTH);
+    }
+
+    #[test]
+    fn a_leaked_enemy_costs_one_health() {
+        let mut scenario = Scenario::with_enemies("Grunt", 1);
+        scenario.run_wave();
+        assert_eq!(scenario.stats().enemies_leaked, 1);
+        assert_eq!(scenario.health(), PLAYER_INITIAL_HEALTH - 1.0);
+    }
+
+    #[test]
+    fn a_flyer_faster_than_its_radius_per_tick_still_leaks() {
+        let mut fast = wave("Wyvern", 1);
+        fast.groups[0].modifiers.speed = 50.0;
+        let mut scenario = Scenario::new(vec![fast]);
+        scenario.run_wave();
+        assert_eq!(scenario.stats().enemies_leaked, 1);
+        assert_eq!(scenario.state(), GameState::Victory);
+    }
+
+    #[test]
+    fn a_double_path_wave_sends_enemies_down_both_paths() {
+        let mut double = wave("Grunt", 1);
+        double.second_path = true;
+        double.groups[0].spawn = SpawnPoint::Index(0);
+        double.groups.push(WaveGroup {
+            spawn: SpawnPoint::Index(1),
+            ..double.groups[0].clone()
+        });
+        let mut scenario = Scenario::new(vec![double, wave("Grunt", 1)]);
+        assert_eq!(path_starts(scenario.app.world_mut()), 2);
+        scenario
+            .start_wave()
+            .run_until(|world| world.iter_entities().any(|e| e.contains::<OnSecondPath>()))
+            .step(64);
+        let world = scenario.app.world_mut();
+        let spline = world.resource::<SecondPath>().spline.clone();
+        let (transform, progress) = world
+            .query_filtered::<(&Transform, &PathProgress), With<OnSecondPath>>()
+            .single(world)
+            .unwrap();
+        assert!(
+            transform
+                .translation
+                .truncate()
+                .distance(spline.position_at(progress.0))
+                < 1e-3
+        );
+
+        scenario.run_until(|world| state(world) == GameState::BeforeWave);
+        assert_eq!(scenario.stats().enemies_leaked, 2);
+        assert_eq!(scenario.state(), GameState::BeforeWave);
+        assert!(!scenario.app.world().contains_resource::<SecondPath>());
+        assert_eq!(path_starts(scenario.app.world_mut()), 1);
+    }
+
+    #[test]
+    fn the_wave_starts_once_the_build_phase_runs_out() {
+        let mut scenario = Scenario::with_enemies("Grunt", 1);
+        scenario.step((BUILD_PHASE_SECONDS as u32 - 1) * 64);
+        assert_eq!(scenario.state(), GameState::BeforeWave);
+        scenario.step(2 * 64);
+        assert_eq!(scenario.state(), GameState::Wave);
+    }
+
+    #[test]
+    fn a_kill_pays_the_enemy_gold() {
+        let mut scenario = Scenario::with_enemies("Grunt", 1);
+        scenario.towers_along_path(TowerKind::Basic, 4).start_wave();
+        let gold = scenario.gold();
+        scenario.run_until(|world| world.resource::<RunStats>().enemies_killed == 1);
+        let kill_value = scenario.stats().kill_value;
+        assert!(kill_value > 0);
+        assert_eq!(scenario.gold(), gold + kill_value);
+    }
+
+    #[test]
+    fn a_finished_wave_leads_to_the_next_build_phase() {
+        let mut scenario = Scenario::new(vec![wave("Grunt", 2), wave("Grunt", 2)]);
+        scenario.towers_along_path(TowerKind::Basic, 4).run_wave();
+        assert_eq!(scenario.state(), GameState::BeforeWave);
+        assert_eq!(scenario.wave(), 1);
+        assert_eq!(scenario.stats().waves_survived, 1);
+    }
+
+    #[test]
+    fn the_last_wave_ends_in_victory() {
+        let mut scenario = Scenario::with_enemies("Grunt", 1);
+        scenario.towers_along_path(TowerKind::Basic, 4).run_wave();
+        assert_eq!(scenario.state(), GameState::Victory);
+    }
+}
@@ -0,0 +1,273 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    assets::{
+        HIGH_SCORE_FILE, HIGH_SCORES_PER_TABLE, SCORE_PAR_SECONDS_PER_WAVE, SCORE_PER_KILL_GOLD,
+        SCORE_PER_LIFE, SCORE_PER_WAVE, SCORE_TIME_BONUS_PER_SECOND,
+    },
+    difficulty::Difficulty,
+    generate_path,
+    grid::{GridIndex, HexGridColumns, HexGridRows},
+    path::HexPath,
+    player::Player,
+    results::{RunStats, spawn_results_screen},
+    ron_file::{RonFile, RonFileError},
+    stats::{Health, Wave},
+};
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_high_scores);
+        app.add_systems(
+            OnEnter(GameState::BeforeWave),
+            record_map_hash.after(generate_path),
+        );
+        app.add_systems(
+            Update,
+            tick_play_time.run_if(in_state(GameState::Wave).or(in_state(GameState::BeforeWave))),
+        );
+        for state in [GameState::GameOver, GameState::Victory] {
+            app.add_systems(OnEnter(state), record_score.before(spawn_results_screen));
+        }
+    }
+}
+
+/// Points of a finished run, by source.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub kills: u32,
+    pub waves: u32,
+    pub lives: u32,
+    pub time_bonus: u32,
+}
+
+impl Score {
+    pub fn new(stats: &RunStats, lives: f32) -> Self {
+        let par = SCORE_PAR_SECONDS_PER_WAVE * stats.waves_survived as f32;
+        Self {
+            kills: stats.kill_value * SCORE_PER_KILL_GOLD,
+            waves: stats.waves_survived * SCORE_PER_WAVE,
+            lives: lives.max(0.0).ceil() as u32 * SCORE_PER_LIFE,
+            time_bonus: ((par - stats.play_seconds).max(0.0) * SCORE_TIME_BONUS_PER_SECOND) as u32,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.kills + self.waves + self.lives + self.time_bonus
+    }
+}
+
+/// Which high-score table a run belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScoreKey {
+    pub map: String,
+    pub difficulty: String,
+}
+
+impl ScoreKey {
+    pub fn new(columns: i32, rows: i32, difficulty: &str) -> Self {
+        Self {
+            map: format!("{columns}x{rows}"),
+            difficulty: difficulty.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HighScoreEntry {
+    pub key: ScoreKey,
+    /// Hash of the grid and the first path of the run, equal for runs played
+    /// on the same map.
+    pub map_hash: u64,
+    pub score: Score,
+    pub waves: u32,
+    pub victory: bool,
+}
+
+/// Best runs, loaded from and saved to `HIGH_SCORE_FILE`.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl RonFile for HighScores {
+    const NAME: &'static str = "high scores";
+}
+
+impl HighScores {
+    /// Entries of a table, best first.
+    pub fn table(&self, key: &ScoreKey) -> Vec<&HighScoreEntry> {
+        let mut table: Vec<&HighScoreEntry> =
+            self.entries.iter().filter(|e| e.key == *key).collect();
+        table.sort_by_key(|e| std::cmp::Reverse(e.score.total()));
+        table
+    }
+
+    /// Adds `entry` to its table and returns its rank, `None` if it did not
+    /// make the table.
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let total = entry.score.total();
+        let (mut table, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|e| e.key == entry.key);
+        table.push(entry);
+        table.sort_by_key(|e| std::cmp::Reverse(e.score.total()));
+        let rank = table
+            .iter()
+            .rposition(|e| e.score.total() == total)
+            .filter(|rank| *rank < HIGH_SCORES_PER_TABLE);
+        table.truncate(HIGH_SCORES_PER_TABLE);
+        self.entries = others;
+        self.entries.extend(table);
+        rank
+    }
+}
+
+/// Score of the run that just ended and its place in the high-score table.
+#[derive(Resource, Debug, Clone)]
+pub struct FinalScore {
+    pub score: Score,
+    pub key: ScoreKey,
+    pub rank: Option<usize>,
+}
+
+pub fn map_hash(columns: i32, rows: i32, path: &HexPath<GridIndex>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (columns, rows).hash(&mut hasher);
+    path.nodes.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn load_high_scores(mut commands: Commands) {
+    let scores = match HighScores::load(HIGH_SCORE_FILE) {
+        Ok(scores) => scores,
+        Err(RonFileError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            HighScores::default()
+        }
+        Err(e) => {
+            warn!("{e}");
+            HighScores::default()
+        }
+    };
+    commands.insert_resource(scores);
+}
+
+pub fn record_map_hash(
+    mut stats: ResMut<RunStats>,
+    wave: Res<Wave>,
+    columns: Res<HexGridColumns>,
+    rows: Res<HexGridRows>,
+    path: Option<Res<HexPath<GridIndex>>>,
+) {
+    if wave.0 == 0
+        && let Some(path) = path
+    {
+        stats.map_hash = map_hash(**columns, **rows, &path);
+    }
+}
+
+pub fn tick_play_time(mut stats: ResMut<RunStats>, time: Res<Time>) {
+    stats.play_seconds += time.delta_secs();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_score(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    stats: Res<RunStats>,
+    mut high_scores: ResMut<HighScores>,
+    player: Query<&Health, With<Player>>,
+    columns: Res<HexGridColumns>,
+    rows: Res<HexGridRows>,
+    difficulty: Res<Difficulty>,
+) {
+    let lives = player.iter().map(|h| h.0).sum();
+    let score = Score::new(&stats, lives);
+    let key = ScoreKey::new(**columns, **rows, difficulty.name());
+    let rank = high_scores.insert(HighScoreEntry {
+        key: key.clone(),
+        map_hash: stats.map_hash,
+        score,
+        waves: stats.waves_survived,
+        victory: *state.get() == GameState::Victory,
+    });
+    info!("final score: {} (rank {rank:?})", score.total());
+    if let Err(e) = high_scores.save(HIGH_SCORE_FILE) {
+        error!("{e}");
+    }
+    commands.insert_resource(FinalScore { score, key, rank });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(map: &str, total: u32) -> HighScoreEntry {
+        HighScoreEntry {
+            key: ScoreKey {
+                map: map.to_string(),
+                difficulty: Difficulty::Normal.name().to_string(),
+            },
+            map_hash: 7,
+            score: Score {
+                kills: total,
+                ..Default::default()
+            },
+            waves: 1,
+            victory: false,
+        }
+    }
+
+    #[test]
+    fn score_adds_up_its_parts() {
+        let stats = RunStats {
+            waves_survived: 2,
+            kill_value: 30,
+            play_seconds: SCORE_PAR_SECONDS_PER_WAVE * 2.0 - 10.0,
+            ..Default::default()
+        };
+        let score = Score::new(&stats, 3.0);
+        assert_eq!(score.kills, 30 * SCORE_PER_KILL_GOLD);
+        assert_eq!(score.waves, 2 * SCORE_PER_WAVE);
+        assert_eq!(score.lives, 3 * SCORE_PER_LIFE);
+        assert_eq!(
+            score.time_bonus,
+            (10.0 * SCORE_TIME_BONUS_PER_SECOND) as u32
+        );
+        assert_eq!(
+            score.total(),
+            score.kills + score.waves + score.lives + score.time_bonus
+        );
+
+        let slow = RunStats {
+            play_seconds: 1e6,
+            ..stats
+        };
+        assert_eq!(Score::new(&slow, -1.0).time_bonus, 0);
+        assert_eq!(Score::new(&slow, -1.0).lives, 0);
+    }
+
+    #[test]
+    fn tables_are_ranked_and_capped_per_key() {
+        let mut scores = HighScores::default();
+        for total in 0..HIGH_SCORES_PER_TABLE as u32 {
+            scores.insert(entry("a", total * 10));
+        }
+        assert_eq!(scores.insert(entry("b", 1)), Some(0));
+        assert_eq!(scores.insert(entry("a", 55)), Some(4));
+        assert_eq!(scores.insert(entry("a", 0)), None);
+
+        let key = entry("a", 0).key;
+        let table = scores.table(&key);
+        assert_eq!(table.len(), HIGH_SCORES_PER_TABLE);
+        assert_eq!(table[0].score.total(), 90);
+        assert_eq!(table.last().unwrap().score.total(), 10);
+        assert_eq!(scores.table(&entry("b", 0).key).len(), 1);
+    }
+}
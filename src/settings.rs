@@ -0,0 +1,96 @@
+use bevy::{
+    app::Plugin,
+    ecs::{component::Component, resource::Resource},
+};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_resource::<Settings>();
+    }
+}
+
+/// Player facing display options.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub health_bars: bool,
+    pub damage_numbers: bool,
+    /// Whether towers can be placed and sold while the game is paused.
+    pub build_while_paused: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            health_bars: true,
+            damage_numbers: true,
+            build_while_paused: true,
+        }
+    }
+}
+
+/// A `Settings` flag that can be flipped from a menu.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingToggle {
+    HealthBars,
+    DamageNumbers,
+    BuildWhilePaused,
+}
+
+impl SettingToggle {
+    pub const ALL: [SettingToggle; 3] = [
+        SettingToggle::HealthBars,
+        SettingToggle::DamageNumbers,
+        SettingToggle::BuildWhilePaused,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingToggle::HealthBars => "health bars",
+            SettingToggle::DamageNumbers => "damage numbers",
+            SettingToggle::BuildWhilePaused => "build while paused",
+        }
+    }
+
+    pub fn is_on(&self, settings: &Settings) -> bool {
+        match self {
+            SettingToggle::HealthBars => settings.health_bars,
+            SettingToggle::DamageNumbers => settings.damage_numbers,
+            SettingToggle::BuildWhilePaused => settings.build_while_paused,
+        }
+    }
+
+    pub fn flip(&self, settings: &mut Settings) {
+        match self {
+            SettingToggle::HealthBars => settings.health_bars = !settings.health_bars,
+            SettingToggle::DamageNumbers => settings.damage_numbers = !settings.damage_numbers,
+            SettingToggle::BuildWhilePaused => {
+                settings.build_while_paused = !settings.build_while_paused
+            }
+        }
+    }
+
+    pub fn label(&self, settings: &Settings) -> String {
+        let state = if self.is_on(settings) { "on" } else { "off" };
+        format!("{}: {state}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_flip_their_own_flag() {
+        let mut settings = Settings::default();
+        SettingToggle::DamageNumbers.flip(&mut settings);
+        assert!(!settings.damage_numbers);
+        assert!(settings.health_bars && settings.build_while_paused);
+        assert_eq!(
+            SettingToggle::DamageNumbers.label(&settings),
+            "damage numbers: off"
+        );
+        assert!(SettingToggle::HealthBars.is_on(&settings));
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::FONT_SIZE,
+    input::InputSet,
+    menu::PauseMenu,
+    ui::{UiFont, UiSet, prepare_ui_overlay},
+};
+
+pub struct SpeedPlugin;
+
+impl Plugin for SpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_speed_buttons.after(prepare_ui_overlay));
+        app.add_systems(
+            Update,
+            speed_hotkeys
+                .in_set(InputSet)
+                .run_if(not(in_state(PauseMenu::Open))),
+        );
+        app.add_systems(Update, update_speed_buttons.in_set(UiSet));
+    }
+}
+
+/// Fast-forward multipliers cycled through with the speed hotkey.
+pub static GAME_SPEEDS: [f32; 3] = [1.0, 2.0, 4.0];
+static BUTTON_COLOR: Color = Color::hsla(0.0, 0.0, 0.2, 0.8);
+static ACTIVE_BUTTON_COLOR: Color = Color::hsla(45.0, 0.8, 0.4, 0.9);
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum SpeedControl {
+    /// Toggles the pause.
+    Pause,
+    /// Runs the game at the given multiplier, resuming it if paused.
+    Speed(f32),
+}
+
+impl SpeedControl {
+    pub fn apply(&self, time: &mut Time<Virtual>) {
+        match *self {
+            SpeedControl::Pause if time.is_paused() => time.unpause(),
+            SpeedControl::Pause => time.pause(),
+            SpeedControl::Speed(speed) => {
+                time.set_relative_speed(speed);
+                time.unpause();
+            }
+        }
+    }
+
+    pub fn is_active(&self, time: &Time<Virtual>) -> bool {
+        match *self {
+            SpeedControl::Pause => time.is_paused(),
+            SpeedControl::Speed(speed) => !time.is_paused() && time.relative_speed() == speed,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            SpeedControl::Pause => "||".to_string(),
+            SpeedControl::Speed(speed) => format!("{speed}x"),
+        }
+    }
+}
+
+/// The speed following the current one, wrapping back to 1x.
+pub fn next_speed(current: f32) -> f32 {
+    GAME_SPEEDS
+        .iter()
+        .copied()
+        .find(|s| *s > current)
+        .unwrap_or(GAME_SPEEDS[0])
+}
+
+pub fn speed_hotkeys(keys: Res<ButtonInput<KeyCode>>, mut time: ResMut<Time<Virtual>>) {
+    if keys.just_pressed(KeyCode::Space) {
+        SpeedControl::Pause.apply(&mut time);
+        info!("paused: {}", time.is_paused());
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        let speed = next_speed(time.relative_speed());
+        SpeedControl::Speed(speed).apply(&mut time);
+        info!("game speed: {speed}x");
+    }
+}
+
+pub fn spawn_speed_buttons(mut commands: Commands, font: Res<UiFont>) {
+    let controls = [SpeedControl::Pause]
+        .into_iter()
+        .chain(GAME_SPEEDS.map(SpeedControl::Speed));
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            column_gap: Val::Px(4.0),
+            ..Default::default()
+        })
+        .with_children(|builder| {
+            for control in controls {
+                builder
+                    .spawn((
+                        Node {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                            ..Default::default()
+                        },
+                        Button,
+                        BackgroundColor(BUTTON_COLOR),
+                        control,
+                    ))
+                    .with_child((
+                        Text::new(control.label()),
+                        TextFont::default()
+                            .with_font(font.0.clone())
+                            .with_font_size(FONT_SIZE),
+                        Pickable::IGNORE,
+                    ))
+                    .observe(on_speed_button_click);
+            }
+        });
+}
+
+fn on_speed_button_click(
+    trigger: Trigger<Pointer<Click>>,
+    controls: Query<&SpeedControl>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if let Ok(control) = controls.get(trigger.target) {
+        control.apply(&mut time);
+    }
+}
+
+pub fn update_speed_buttons(
+    buttons: Query<(&SpeedControl, &mut BackgroundColor)>,
+    time: Res<Time<Virtual>>,
+) {
+    for (control, mut color) in buttons {
+        color.0 = if control.is_active(&time) {
+            ACTIVE_BUTTON_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controls_drive_virtual_time() {
+        let mut time = Time::<Virtual>::default();
+        SpeedControl::Speed(4.0).apply(&mut time);
+        assert_eq!(time.relative_speed(), 4.0);
+        assert!(SpeedControl::Speed(4.0).is_active(&time));
+        SpeedControl::Pause.apply(&mut time);
+        assert!(time.is_paused());
+        assert!(!SpeedControl::Speed(4.0).is_active(&time));
+        SpeedControl::Speed(2.0).apply(&mut time);
+        assert!(!time.is_paused());
+        assert_eq!(time.relative_speed(), 2.0);
+    }
+
+    #[test]
+    fn speeds_cycle() {
+        assert_eq!(next_speed(1.0), 2.0);
+        assert_eq!(next_speed(2.0), 4.0);
+        assert_eq!(next_speed(4.0), 1.0);
+    }
+}
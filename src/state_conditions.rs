@@ -1,8 +1,9 @@
 use bevy::{
+    asset::Assets,
     ecs::{
         query::With,
         schedule::{IntoScheduleConfigs, ScheduleConfigs},
-        system::{IntoSystem, Query, Res, ResMut, ScheduleSystem, System},
+        system::{Query, Res, ResMut, ScheduleSystem},
     },
     log::info,
     state::state::NextState,
@@ -10,17 +11,65 @@ use bevy::{
 
 use crate::{
     GameState,
-    enemy::{Enemy, SpawnCounter},
-    player::Player,
-    stats::Health,
+    difficulty::Difficulty,
+    enemy::Enemy,
+    path::PathMode,
+    player::{Player, game_running},
+    results::RunStats,
+    stats::{Health, Wave},
+    wave::{WaveSchedule, WaveScript, WaveScriptHandle},
 };
 
 pub fn wave_done(
-    spawn: Res<SpawnCounter>,
+    schedule: Res<WaveSchedule>,
     enemies: Query<(), With<Enemy>>,
     player: Query<&Health, With<Player>>,
 ) -> bool {
-    (!spawn.can_spawn() && enemies.is_empty()) || player.iter().all(|h| h.0 <= 0.0)
+    (schedule.is_exhausted() && enemies.is_empty()) || player.iter().all(|h| h.0 <= 0.0)
+}
+
+/// State following a finished wave, `completed_waves` including the wave
+/// that just ended.
+pub fn state_after_wave(
+    player_alive: bool,
+    completed_waves: u32,
+    scripted_waves: usize,
+) -> GameState {
+    if !player_alive {
+        GameState::GameOver
+    } else if completed_waves as usize >= scripted_waves {
+        GameState::Victory
+    } else {
+        GameState::BeforeWave
+    }
+}
+
+pub fn advance_after_wave(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut stats: ResMut<RunStats>,
+    player: Query<&Health, With<Player>>,
+    wave: Res<Wave>,
+    script: Res<WaveScriptHandle>,
+    scripts: Res<Assets<WaveScript>>,
+    difficulty: Res<Difficulty>,
+) {
+    let alive = game_running(player);
+    if alive {
+        stats.waves_survived += 1;
+    }
+    let scripted_waves = if difficulty.settings().endless {
+        usize::MAX
+    } else {
+        scripts.get(&script.0).map_or(0, |s| s.waves.len())
+    };
+    let state = state_after_wave(alive, wave.0, scripted_waves);
+    info!("changing state: {state:?}");
+    next_state.set(state);
+}
+
+/// Whether the current path has to make way for a new one before the next wave.
+pub fn new_path_next_wave(mode: Res<PathMode>, wave: Res<Wave>) -> bool {
+    mode.new_path_for(wave.0)
 }
 
 pub fn change_state(state: GameState) -> ScheduleConfigs<ScheduleSystem> {
@@ -30,3 +79,16 @@ pub fn change_state(state: GameState) -> ScheduleConfigs<ScheduleSystem> {
     })
     .into_configs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_waves_lead_to_next_state() {
+        assert_eq!(state_after_wave(true, 3, 10), GameState::BeforeWave);
+        assert_eq!(state_after_wave(true, 10, 10), GameState::Victory);
+        assert_eq!(state_after_wave(false, 10, 10), GameState::GameOver);
+        assert_eq!(state_after_wave(false, 3, 10), GameState::GameOver);
+    }
+}
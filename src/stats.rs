@@ -1,8 +1,15 @@
+use std::ops::{Add, AddAssign};
+
 use bevy::{
+    color::Color,
     ecs::{component::Component, resource::Resource},
-    prelude::Deref,
+    prelude::{Deref, DerefMut},
     time::Timer,
 };
+use rand::{SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::{EXPLOSIVE_DAMAGE_COLOR, PHYSICAL_DAMAGE_COLOR};
 
 #[derive(Component, Deref, Clone, Copy)]
 pub struct Damage(pub f32);
@@ -13,9 +20,124 @@ pub struct FireRate(pub f32, pub Option<Timer>);
 #[derive(Component)]
 pub struct Health(pub f32);
 #[derive(Component)]
+pub struct MaxHealth(pub f32);
+#[derive(Component)]
 pub struct Speed(pub f32);
 #[derive(Component)]
 pub struct Armor(pub f32);
 
+impl Armor {
+    /// Damage left after armor, 100 armor halving incoming damage.
+    pub fn reduce(&self, amount: f32) -> f32 {
+        amount * 100.0 / (100.0 + self.0.max(0.0))
+    }
+}
+
+/// Kind of damage a tower deals, used to colour damage numbers.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Explosive,
+}
+
+impl DamageType {
+    pub fn color(&self) -> Color {
+        match self {
+            DamageType::Physical => PHYSICAL_DAMAGE_COLOR,
+            DamageType::Explosive => EXPLOSIVE_DAMAGE_COLOR,
+        }
+    }
+}
+
 #[derive(Resource, Deref, Debug)]
 pub struct Wave(pub u32);
+
+/// Seed of the current run. Paths and endless waves are derived from it, so
+/// two runs with the same seed play out on the same map.
+#[derive(Resource, Deref, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunSeed(pub u64);
+
+impl Default for RunSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl RunSeed {
+    /// Seed for everything generated before `wave`.
+    pub fn for_wave(&self, wave: u32) -> u64 {
+        self.0 ^ (wave as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+/// Rolls made while playing, like tower stats and spawn points. Reseeded from
+/// the `RunSeed` before every wave so replays roll the same numbers.
+#[derive(Resource, Deref, DerefMut)]
+pub struct RunRng(pub StdRng);
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(0))
+    }
+}
+
+impl RunRng {
+    pub fn for_wave(seed: &RunSeed, wave: u32) -> Self {
+        Self(StdRng::seed_from_u64(seed.for_wave(wave).rotate_left(31)))
+    }
+}
+
+/// Stats a tower was built with. The effective `Damage`, `Range` and `FireRate`
+/// components are derived from these and never written to directly.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TowerBaseStats {
+    pub damage: f32,
+    pub range: f32,
+    pub fire_rate: f32,
+}
+
+/// Relative stat changes, `0.1` meaning +10%.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct StatBonus {
+    pub damage: f32,
+    pub range: f32,
+    pub fire_rate: f32,
+}
+
+impl StatBonus {
+    pub fn apply(&self, base: &TowerBaseStats) -> TowerBaseStats {
+        TowerBaseStats {
+            damage: base.damage * (1.0 + self.damage).max(0.0),
+            range: base.range * (1.0 + self.range).max(0.0),
+            fire_rate: base.fire_rate * (1.0 + self.fire_rate).max(0.0),
+        }
+    }
+
+    pub fn scaled(&self, factor: f32) -> StatBonus {
+        StatBonus {
+            damage: self.damage * factor,
+            range: self.range * factor,
+            fire_rate: self.fire_rate * factor,
+        }
+    }
+}
+
+impl Add for StatBonus {
+    type Output = StatBonus;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        StatBonus {
+            damage: self.damage + rhs.damage,
+            range: self.range + rhs.range,
+            fire_rate: self.fire_rate + rhs.fire_rate,
+        }
+    }
+}
+
+impl AddAssign for StatBonus {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
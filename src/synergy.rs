@@ -0,0 +1,304 @@
+use bevy::{
+    app::{FixedUpdate, Plugin, Startup, Update},
+    asset::{Asset, AssetApp, AssetServer, Handle},
+    color::Color,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        query::{Changed, With},
+        removal_detection::RemovedComponents,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    gizmos::gizmos::Gizmos,
+    log::debug,
+    math::Vec3Swizzles,
+    platform::collections::{HashMap, HashSet},
+    prelude::Deref,
+    reflect::TypePath,
+    transform::components::GlobalTransform,
+};
+use serde::Deserialize;
+
+use crate::{
+    assets::{ELITE_LINK_COLOR, RonAssetLoader, SYNERGY_LINK_COLOR, SYNERGY_RULES, apply_loaded},
+    grid::{GridEntry, GridIndex, HexHashGrid},
+    stats::StatBonus,
+    tower::{Tower, TowerIndex, TowerKind},
+};
+
+pub struct SynergyPlugin;
+
+impl Plugin for SynergyPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_asset::<SynergyRules>();
+        app.register_asset_loader(RonAssetLoader::<SynergyRules>::new(&["synergy.ron"]));
+        app.insert_resource(SynergyRules::default());
+        app.insert_resource(SynergyLinks::default());
+        app.add_systems(Startup, load_synergy_rules);
+        app.add_systems(Update, apply_loaded::<SynergyRulesHandle, SynergyRules>);
+        app.add_systems(FixedUpdate, evaluate_synergies);
+    }
+}
+
+/// Draws the links between towers with an active synergy.
+pub struct SynergyLinksPlugin;
+
+impl Plugin for SynergyLinksPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(Update, draw_synergy_links);
+    }
+}
+
+/// Which neighbours a rule needs to become active.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SynergyPattern {
+    /// At least `count` adjacent towers of `other`, `None` meaning the tower's own kind.
+    Adjacent {
+        other: Option<TowerKind>,
+        count: usize,
+    },
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SynergyEffect {
+    pub stats: StatBonus,
+    pub slow: f32,
+    pub vulnerability: f32,
+}
+
+impl SynergyEffect {
+    pub fn combine(self, rhs: SynergyEffect) -> SynergyEffect {
+        SynergyEffect {
+            stats: self.stats + rhs.stats,
+            slow: self.slow + rhs.slow,
+            vulnerability: self.vulnerability + rhs.vulnerability,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct SynergyRule {
+    pub name: String,
+    /// Kind of tower receiving the bonus, `None` for every kind.
+    pub kind: Option<TowerKind>,
+    pub pattern: SynergyPattern,
+    pub effect: SynergyEffect,
+    #[serde(default)]
+    pub elite: bool,
+}
+
+/// Synergy rules, loaded from `SYNERGY_RULES`.
+#[derive(Asset, Resource, TypePath, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SynergyRules {
+    pub rules: Vec<SynergyRule>,
+}
+
+#[derive(Resource, Deref)]
+pub struct SynergyRulesHandle(pub Handle<SynergyRules>);
+
+/// Sum of all synergy effects active on a tower.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SynergyBonus(pub SynergyEffect);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SynergyLink {
+    pub from: Entity,
+    pub to: Entity,
+    pub elite: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct SynergyLinks(pub HashSet<SynergyLink>);
+
+/// Returns the neighbours satisfying `rule` for a tower of `kind` at `index`, or
+/// `None` if the rule does not apply.
+pub fn match_rule(
+    rule: &SynergyRule,
+    index: GridIndex,
+    kind: TowerKind,
+    grid: &HexHashGrid,
+    kinds: &HashMap<GridIndex, (Entity, TowerKind)>,
+) -> Option<Vec<Entity>> {
+    if rule.kind.is_some_and(|k| k != kind) {
+        return None;
+    }
+    match rule.pattern {
+        SynergyPattern::Adjacent { other, count } => {
+            let wanted = other.unwrap_or(kind);
+            let partners: Vec<Entity> = index
+                .neighbours()
+                .filter(|n| grid.contains(n) && grid[*n] == GridEntry::Tower)
+                .filter_map(|n| kinds.get(&n))
+                .filter(|(_, k)| *k == wanted)
+                .map(|(e, _)| *e)
+                .collect();
+            (partners.len() >= count).then_some(partners)
+        }
+    }
+}
+
+pub fn load_synergy_rules(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SynergyRulesHandle(asset_server.load(SYNERGY_RULES)));
+}
+
+/// Re-evaluates all synergy rules whenever a tower is placed, moved or sold,
+/// or the rules are reloaded.
+pub fn evaluate_synergies(
+    moved: Query<(), (With<Tower>, Changed<TowerIndex>)>,
+    mut removed: RemovedComponents<Tower>,
+    mut towers: Query<(Entity, &TowerIndex, &TowerKind, &mut SynergyBonus), With<Tower>>,
+    grid: Res<HexHashGrid>,
+    rules: Res<SynergyRules>,
+    mut links: ResMut<SynergyLinks>,
+) {
+    if moved.is_empty() && removed.read().count() == 0 && !rules.is_changed() {
+        return;
+    }
+    let kinds: HashMap<GridIndex, (Entity, TowerKind)> =
+        towers.iter().map(|(e, i, k, _)| (i.0, (e, *k))).collect();
+    links.0.clear();
+    for (tower, index, kind, mut bonus) in &mut towers {
+        let mut effect = SynergyEffect::default();
+        for rule in &rules.rules {
+            let Some(partners) = match_rule(rule, index.0, *kind, &grid, &kinds) else {
+                continue;
+            };
+            debug!("synergy {} active at {:?}", rule.name, index.0);
+            effect = effect.combine(rule.effect);
+            for partner in partners {
+                let (from, to) = if tower < partner {
+                    (tower, partner)
+                } else {
+                    (partner, tower)
+                };
+                links.0.insert(SynergyLink {
+                    from,
+                    to,
+                    elite: rule.elite,
+                });
+            }
+        }
+        if bonus.0 != effect {
+            bonus.0 = effect;
+        }
+    }
+}
+
+pub fn draw_synergy_links(
+    mut gizmos: Gizmos,
+    links: Res<SynergyLinks>,
+    towers: Query<&GlobalTransform, With<Tower>>,
+) {
+    for link in &links.0 {
+        let (Ok(from), Ok(to)) = (towers.get(link.from), towers.get(link.to)) else {
+            continue;
+        };
+        let color: Color = if link.elite {
+            ELITE_LINK_COLOR
+        } else {
+            SYNERGY_LINK_COLOR
+        };
+        gizmos.line_2d(from.translation().xy(), to.translation().xy(), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::world::World;
+
+    use super::*;
+
+    fn adjacent(kind: Option<TowerKind>, other: Option<TowerKind>, count: usize) -> SynergyRule {
+        SynergyRule {
+            name: "test".to_string(),
+            kind,
+            pattern: SynergyPattern::Adjacent { other, count },
+            effect: SynergyEffect::default(),
+            elite: false,
+        }
+    }
+
+    fn layout(
+        entries: &[(GridIndex, TowerKind)],
+    ) -> (HexHashGrid, HashMap<GridIndex, (Entity, TowerKind)>) {
+        let mut grid = HexHashGrid::new();
+        let mut kinds = HashMap::new();
+        for (n, (index, kind)) in entries.iter().enumerate() {
+            grid[*index] = GridEntry::Tower;
+            kinds.insert(*index, (Entity::from_raw(n as u32), *kind));
+        }
+        (grid, kinds)
+    }
+
+    #[test]
+    fn frost_pair_needs_adjacent_frost() {
+        let rule = &adjacent(Some(TowerKind::Frost), Some(TowerKind::Frost), 1);
+        let center = GridIndex::new(0, 0);
+        let (grid, kinds) = layout(&[
+            (center, TowerKind::Frost),
+            (GridIndex::new(2, 0), TowerKind::Frost),
+        ]);
+        assert!(match_rule(rule, center, TowerKind::Frost, &grid, &kinds).is_none());
+
+        let (grid, kinds) = layout(&[
+            (center, TowerKind::Frost),
+            (GridIndex::new(1, 0), TowerKind::Frost),
+        ]);
+        let partners = match_rule(rule, center, TowerKind::Frost, &grid, &kinds).unwrap();
+        assert_eq!(partners.len(), 1);
+    }
+
+    #[test]
+    fn elite_ring_needs_all_neighbours() {
+        let rule = &adjacent(None, None, 6);
+        let center = GridIndex::new(0, 0);
+        let mut entries: Vec<(GridIndex, TowerKind)> =
+            center.neighbours().map(|n| (n, TowerKind::Basic)).collect();
+        entries.push((center, TowerKind::Basic));
+        let (grid, kinds) = layout(&entries);
+        assert!(match_rule(rule, center, TowerKind::Basic, &grid, &kinds).is_some());
+
+        entries[0].1 = TowerKind::Frost;
+        let (grid, kinds) = layout(&entries);
+        assert!(match_rule(rule, center, TowerKind::Basic, &grid, &kinds).is_none());
+    }
+
+    #[test]
+    fn reloaded_rules_apply_to_placed_towers() {
+        let mut world = World::new();
+        let (grid, kinds) = layout(&[
+            (GridIndex::new(0, 0), TowerKind::Frost),
+            (GridIndex::new(1, 0), TowerKind::Frost),
+        ]);
+        world.insert_resource(grid);
+        world.insert_resource(SynergyRules::default());
+        world.insert_resource(SynergyLinks::default());
+        for (index, (_, kind)) in kinds {
+            world.spawn((Tower, TowerIndex(index), kind, SynergyBonus::default()));
+        }
+        world.run_system_cached(evaluate_synergies).unwrap();
+        assert!(world.resource::<SynergyLinks>().0.is_empty());
+
+        let mut rule = adjacent(Some(TowerKind::Frost), Some(TowerKind::Frost), 1);
+        rule.effect.slow = 0.2;
+        world.resource_mut::<SynergyRules>().rules = vec![rule];
+        world.run_system_cached(evaluate_synergies).unwrap();
+        assert_eq!(world.resource::<SynergyLinks>().0.len(), 1);
+        let mut bonuses = world.query::<&SynergyBonus>();
+        assert!(bonuses.iter(&world).all(|b| b.0.slow == 0.2));
+    }
+
+    #[test]
+    fn shipped_rules_parse() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/synergy/default.synergy.ron"
+        );
+        let text = std::fs::read_to_string(path).unwrap();
+        let shipped: SynergyRules = ron::de::from_str(&text).unwrap();
+        assert!(!shipped.rules.is_empty());
+    }
+}
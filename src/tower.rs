@@ -2,13 +2,14 @@ use std::{collections::HashSet, time::Duration};
 
 use bevy::{
     asset::{AssetServer, Assets, Handle},
-    audio::{AudioPlayer, AudioSource, PlaybackSettings},
+    audio::AudioSource,
+    color::Color,
     ecs::{
         component::Component,
         entity::Entity,
         hierarchy::ChildOf,
         observer::Trigger,
-        query::{QueryData, With, Without},
+        query::{Added, Changed, Has, Or, QueryData, With, Without},
         resource::Resource,
         system::{Commands, Query, Res, ResMut},
         traversal::Traversal,
@@ -17,11 +18,13 @@ use bevy::{
     log::{debug, error, info},
     math::{
         Vec2, Vec3, Vec3Swizzles,
+        ops::sqrt,
         primitives::{Annulus, Circle},
     },
     picking::{
         Pickable,
-        events::{Out, Over, Pointer},
+        events::{Click, Out, Over, Pointer},
+        pointer::PointerButton,
     },
     prelude::{Deref, DerefMut},
     render::mesh::{Mesh, Mesh2d},
@@ -29,17 +32,27 @@ use bevy::{
     time::{Time, Timer, TimerMode},
     transform::components::{GlobalTransform, Transform},
 };
-use rand::random_range;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    ability::{Detector, Revealed, Stealth, is_targetable},
     assets::{
-        BASE_TOWER, PROJECTILE_COLOR, PROJECTILE_SIZE, PROJECTILE_SPEED, RANGE_INDICATOR_COLOR,
-        SHOT_SOUND,
+        BASE_TOWER, BASIC_TOWER_COLOR, CURSE_TOWER_COLOR, FLAK_TOWER_COLOR, FROST_TOWER_COLOR,
+        PROJECTILE_COLOR, PROJECTILE_SIZE, PROJECTILE_SPEED, RANGE_INDICATOR_COLOR, SHOT_SOUND,
+        SUPPORT_TOWER_COLOR,
+    },
+    aura::{AuraBonus, DebuffAura, SupportAura},
+    enemy::{
+        DamageTaken, Enemy, EnemyLayer, EnemyMoved, EnemySize, FlightPath, OnSecondPath,
+        PathProgress, enemy_route,
     },
-    enemy::{DamageTaken, Enemy, EnemyMoved, EnemySize},
     grid::{GridIndex, HexGridRenderRadius, HexSpatialGrid},
+    path::{SecondPath, spline::PathSpline},
     player::Gold,
-    stats::{Damage, FireRate, Range, Speed},
+    stats::{Damage, DamageType, FireRate, Range, Speed, StatBonus, TowerBaseStats},
+    synergy::SynergyBonus,
+    veterancy::{TowerRank, TowerRecord},
 };
 #[derive(Resource, Deref)]
 pub struct TowerRangeIndicatorMesh(pub Handle<Mesh>);
@@ -49,12 +62,90 @@ pub struct TowerRangeIndicatorMaterial(pub Handle<ColorMaterial>);
 #[derive(Component)]
 pub struct Projectile {
     start: Vec2,
+    source: Entity,
+    filter: TargetFilter,
+    kind: DamageType,
 }
 #[derive(Component)]
 pub struct ProjectileDirection(pub Vec2);
 
 #[derive(Component)]
 pub struct Tower;
+#[derive(Component, Deref, Clone, Copy, Debug)]
+pub struct TowerIndex(pub GridIndex);
+
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TowerKind {
+    #[default]
+    Basic,
+    Support,
+    Frost,
+    Curse,
+    Flak,
+}
+
+impl TowerKind {
+    pub const ALL: [TowerKind; 5] = [
+        TowerKind::Basic,
+        TowerKind::Support,
+        TowerKind::Frost,
+        TowerKind::Curse,
+        TowerKind::Flak,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TowerKind::Basic => "Basic",
+            TowerKind::Support => "Support",
+            TowerKind::Frost => "Frost",
+            TowerKind::Curse => "Curse",
+            TowerKind::Flak => "Flak",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            TowerKind::Basic => BASIC_TOWER_COLOR,
+            TowerKind::Support => SUPPORT_TOWER_COLOR,
+            TowerKind::Frost => FROST_TOWER_COLOR,
+            TowerKind::Curse => CURSE_TOWER_COLOR,
+            TowerKind::Flak => FLAK_TOWER_COLOR,
+        }
+    }
+}
+
+/// Which enemy layers a shooting tower can target.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetFilter {
+    #[default]
+    Ground,
+    Air,
+    All,
+}
+
+impl TargetFilter {
+    pub fn accepts(&self, layer: EnemyLayer) -> bool {
+        match self {
+            TargetFilter::Ground => layer == EnemyLayer::Ground,
+            TargetFilter::Air => layer == EnemyLayer::Air,
+            TargetFilter::All => true,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetFilter::Ground => "ground",
+            TargetFilter::Air => "air",
+            TargetFilter::All => "ground and air",
+        }
+    }
+}
+
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct SelectedTowerKind(pub TowerKind);
+/// Tower shown in the info panel.
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct SelectedTower(pub Option<Entity>);
 #[derive(Component, Default, Deref, DerefMut)]
 pub struct TargetsInRange(pub HashSet<Entity>);
 #[derive(Resource)]
@@ -88,39 +179,107 @@ pub fn init_tower_resources(
 ) {
     let mesh = meshes.add(Circle::new(PROJECTILE_SIZE));
     let color = materials.add(PROJECTILE_COLOR);
-    let shot_sound = asset_server.load(SHOT_SOUND);
     let base_tower = asset_server.load(BASE_TOWER);
     let range_indicator = meshes.add(Annulus::new(0.99, 1.0));
     let range_material = materials.add(RANGE_INDICATOR_COLOR);
 
     commands.insert_resource(ProjectilMesh(mesh));
     commands.insert_resource(ProjectilColor(color));
-    commands.insert_resource(ShotSound(shot_sound));
     commands.insert_resource(BaseTowerImage(base_tower));
     commands.insert_resource(TowerRangeIndicatorMaterial(range_material));
     commands.insert_resource(TowerRangeIndicatorMesh(range_indicator));
 }
 
+pub fn load_shot_sound(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ShotSound(asset_server.load(SHOT_SOUND)));
+}
+
+/// Derives the effective tower stats from `TowerBaseStats` and every active bonus.
+#[allow(clippy::type_complexity)]
+pub fn update_effective_stats(
+    query: Query<
+        (
+            &TowerBaseStats,
+            &AuraBonus,
+            &SynergyBonus,
+            &TowerRank,
+            &mut Damage,
+            &mut Range,
+            &mut FireRate,
+        ),
+        Or<(
+            Added<TowerBaseStats>,
+            Changed<AuraBonus>,
+            Changed<SynergyBonus>,
+            Changed<TowerRank>,
+        )>,
+    >,
+) {
+    for (base, aura, synergy, rank, mut damage, mut range, mut fire_rate) in query {
+        let bonus = aura.0 + synergy.0.stats + rank.bonus();
+        let effective = bonus.apply(base);
+        damage.0 = effective.damage;
+        range.0 = effective.range;
+        fire_rate.0 = effective.fire_rate;
+        if let Some(timer) = &mut fire_rate.1 {
+            timer.set_duration(Duration::from_secs_f32(60.0 / effective.fire_rate));
+        }
+    }
+}
+
+/// Shoots at the enemy in range that is furthest along its route, the one
+/// closest to leaking.
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn update_tower(
     mut commands: Commands,
-    query: Query<(&mut FireRate, &Damage, &Range, &GlobalTransform), (With<Tower>, Without<Enemy>)>,
-    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    query: Query<
+        (
+            Entity,
+            &mut FireRate,
+            &Damage,
+            &Range,
+            &GlobalTransform,
+            &TargetFilter,
+            &DamageType,
+        ),
+        (With<Tower>, Without<Enemy>),
+    >,
+    enemies: Query<
+        (
+            &Transform,
+            &EnemyLayer,
+            &PathProgress,
+            Option<&FlightPath>,
+            Has<OnSecondPath>,
+            Has<Stealth>,
+            Has<Revealed>,
+        ),
+        With<Enemy>,
+    >,
+    spline: Res<PathSpline>,
+    second: Option<Res<SecondPath>>,
     projectile_mesh: Res<ProjectilMesh>,
     projectile_material: Res<ProjectilColor>,
     time: Res<Time>,
     //shot_sound: Res<ShotSound>,
 ) {
     let delta = time.delta();
-    for (mut fr, d, r, e) in query {
+    for (tower, mut fr, d, r, e, filter, kind) in query {
         let position = e.translation().xy();
 
-        if let Some((_e, t)) = enemies.iter().min_by(|(_, rhs_t), (_, lhs_t)| {
-            let d1 = rhs_t.translation.xy().distance_squared(position);
-            let d2 = lhs_t.translation.xy().distance_squared(position);
-            d1.partial_cmp(&d2).unwrap_or(std::cmp::Ordering::Equal)
-        }) && t.translation.xy().distance(position) <= r.0
-        {
+        let target = enemies
+            .iter()
+            .filter(|(t, layer, .., stealth, revealed)| {
+                filter.accepts(**layer)
+                    && is_targetable(*stealth, *revealed)
+                    && t.translation.xy().distance(position) <= r.0
+            })
+            .map(|(t, _, progress, flight, on_second_path, ..)| {
+                let route = enemy_route(&spline, second.as_deref(), flight, on_second_path);
+                (t, route.progress(progress.0))
+            })
+            .max_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs));
+        if let Some((t, _)) = target {
             let mut timer_spawned = false;
             let timer = if let Some(timer) = &mut fr.1 {
                 timer
@@ -141,7 +300,12 @@ pub fn update_tower(
                         Mesh2d(projectile_mesh.0.clone()),
                         MeshMaterial2d(projectile_material.0.clone()),
                         transform,
-                        Projectile { start: position },
+                        Projectile {
+                            start: position,
+                            source: tower,
+                            filter: *filter,
+                            kind: *kind,
+                        },
                         ProjectileDirection(dir.normalize()),
                         Speed(PROJECTILE_SPEED),
                         TargetsInRange::default(),
@@ -191,7 +355,16 @@ pub fn update_projectiles(
         ),
         Without<Enemy>,
     >,
-    mut enemies: Query<(&Transform, &EnemySize), With<Enemy>>,
+    mut enemies: Query<
+        (
+            &Transform,
+            &EnemySize,
+            &EnemyLayer,
+            Has<Stealth>,
+            Has<Revealed>,
+        ),
+        With<Enemy>,
+    >,
     time: Res<Time>,
     mut spatial_grid: ResMut<HexSpatialGrid>,
     size: Res<HexGridRenderRadius>,
@@ -206,54 +379,168 @@ pub fn update_projectiles(
         let nearby = spatial_grid.get_nearby(&grid_index);
 
         for enemy_entity in nearby {
-            let Ok((e_t, size)) = enemies.get_mut(enemy_entity) else {
+            let Ok((e_t, size, layer, stealth, revealed)) = enemies.get_mut(enemy_entity) else {
                 continue;
             };
+            if !p.filter.accepts(*layer) || !is_targetable(stealth, revealed) {
+                continue;
+            }
             if check_collision(e_t, t.as_ref(), size.0) {
-                commands.trigger_targets(DamageTaken { amount: d.0 }, enemy_entity);
+                commands.trigger_targets(
+                    DamageTaken {
+                        amount: d.0,
+                        kind: p.kind,
+                        source: Some(p.source),
+                    },
+                    enemy_entity,
+                );
                 commands.entity(e).despawn();
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_tower_at(
     entity: Entity,
+    index: GridIndex,
+    kind: TowerKind,
     mut commands: Commands,
     base_tower_image: Res<BaseTowerImage>,
     tower_cost: &Gold,
     player_gold: &mut Gold,
+    tile_size: f32,
+    rng: &mut impl Rng,
 ) -> bool {
     if player_gold.0 >= tower_cost.0 {
         player_gold.0 -= tower_cost.0;
-        let damage = random_range(5.0..=15.0);
-        let range = random_range(150.0..=350.0);
-        let fire_rate = random_range(30.0..=90.0);
-        commands
-            .entity(entity)
-            .with_child((
-                Tower,
-                Damage(damage),
-                Range(range),
-                FireRate(fire_rate, None),
-                Sprite {
-                    image: base_tower_image.0.clone(),
-                    custom_size: Some(Vec2::new(40.0, 40.0)),
-                    ..Default::default()
-                },
-                Transform::from_xyz(0.0, 0.0, 5.0),
-                Pickable {
-                    should_block_lower: false,
-                    is_hoverable: true,
-                },
-            ))
-            .observe(on_tower_hover)
-            .observe(on_tower_out);
+        spawn_tower(
+            entity,
+            index,
+            kind,
+            &mut commands,
+            &base_tower_image,
+            tile_size,
+            rng,
+        );
         true
     } else {
         false
     }
 }
+
+/// Spawns a tower of `kind` on the grid tile `entity` with stats rolled from
+/// `rng`.
+pub fn spawn_tower(
+    entity: Entity,
+    index: GridIndex,
+    kind: TowerKind,
+    commands: &mut Commands,
+    base_tower_image: &BaseTowerImage,
+    tile_size: f32,
+    rng: &mut impl Rng,
+) -> Entity {
+    let mut tower = commands.spawn((
+        Tower,
+        kind,
+        TowerIndex(index),
+        SynergyBonus::default(),
+        ChildOf(entity),
+        Sprite {
+            image: base_tower_image.0.clone(),
+            custom_size: Some(Vec2::new(40.0, 40.0)),
+            color: kind.color(),
+            ..Default::default()
+        },
+        Transform::from_xyz(0.0, 0.0, 5.0),
+        Pickable {
+            should_block_lower: false,
+            is_hoverable: true,
+        },
+    ));
+    match kind {
+        TowerKind::Basic => {
+            let base = TowerBaseStats {
+                damage: rng.random_range(5.0..=15.0),
+                range: rng.random_range(150.0..=350.0),
+                fire_rate: rng.random_range(30.0..=90.0),
+            };
+            tower.insert((
+                base,
+                TargetFilter::Ground,
+                DamageType::Physical,
+                AuraBonus::default(),
+                TowerRecord::default(),
+                TowerRank::default(),
+                Damage(base.damage),
+                Range(base.range),
+                FireRate(base.fire_rate, None),
+            ));
+        }
+        TowerKind::Flak => {
+            let base = TowerBaseStats {
+                damage: rng.random_range(4.0..=8.0),
+                range: rng.random_range(250.0..=400.0),
+                fire_rate: rng.random_range(90.0..=150.0),
+            };
+            tower.insert((
+                base,
+                TargetFilter::Air,
+                DamageType::Explosive,
+                AuraBonus::default(),
+                TowerRecord::default(),
+                TowerRank::default(),
+                Damage(base.damage),
+                Range(base.range),
+                FireRate(base.fire_rate, None),
+            ));
+        }
+        TowerKind::Support => {
+            let aura = SupportAura {
+                radius: 1,
+                bonus: StatBonus {
+                    damage: 0.2,
+                    range: 0.1,
+                    fire_rate: 0.15,
+                },
+            };
+            tower.insert((aura, Range(aura_world_radius(aura.radius, tile_size))));
+        }
+        TowerKind::Frost => {
+            let aura = DebuffAura {
+                radius: 2,
+                slow: 0.35,
+                vulnerability: 0.0,
+            };
+            tower.insert((aura, Range(aura_world_radius(aura.radius, tile_size))));
+        }
+        TowerKind::Curse => {
+            let aura = DebuffAura {
+                radius: 1,
+                slow: 0.0,
+                vulnerability: 0.3,
+            };
+            tower.insert((
+                aura,
+                Detector {
+                    radius: aura.radius + 1,
+                },
+                Range(aura_world_radius(aura.radius, tile_size)),
+            ));
+        }
+    }
+    tower
+        .observe(on_tower_hover)
+        .observe(on_tower_out)
+        .observe(on_tower_click)
+        .id()
+}
+
+/// Radius in world units covering every hex up to `radius` steps away.
+pub fn aura_world_radius(radius: i32, tile_size: f32) -> f32 {
+    (radius as f32 + 0.5) * sqrt(3.0) * tile_size
+}
+
 #[derive(Component)]
 pub struct Indicator;
 fn on_tower_hover(
@@ -288,6 +575,12 @@ fn on_tower_out(
     }
 }
 
+fn on_tower_click(trigger: Trigger<Pointer<Click>>, mut selected: ResMut<SelectedTower>) {
+    if trigger.button == PointerButton::Primary {
+        selected.0 = Some(trigger.target);
+    }
+}
+
 //fn on_shot(trigger: Trigger<OnShot>, position)
 fn check_collision(
     enemy_transform: &Transform,
@@ -300,3 +593,48 @@ fn check_collision(
         .distance(projectile_transform.translation.xy())
         < size
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::world::World;
+
+    use super::*;
+
+    #[test]
+    fn towers_shoot_the_enemy_closest_to_the_exit() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(ProjectilMesh(Handle::default()));
+        world.insert_resource(ProjectilColor(Handle::default()));
+        world.insert_resource(PathSpline::straight(
+            Vec2::ZERO,
+            GridIndex::new(10, 0),
+            10.0,
+        ));
+        world.spawn((
+            Tower,
+            FireRate(60.0, None),
+            Damage(1.0),
+            Range(100.0),
+            GlobalTransform::default(),
+            TargetFilter::Ground,
+            DamageType::Physical,
+        ));
+        let enemy = |x: f32, progress: f32| {
+            (
+                Enemy,
+                Transform::from_xyz(x, 0.0, 0.0),
+                EnemyLayer::Ground,
+                PathProgress(progress),
+            )
+        };
+        world.spawn(enemy(10.0, 5.0));
+        world.spawn(enemy(-50.0, 80.0));
+        world.spawn(enemy(-150.0, 120.0));
+        world.run_system_cached(update_tower).unwrap();
+
+        let mut shots = world.query::<&ProjectileDirection>();
+        let shot = shots.single(&world).unwrap();
+        assert_eq!(shot.0, Vec2::NEG_X);
+    }
+}
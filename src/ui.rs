@@ -1,10 +1,18 @@
 use bevy::{ecs::relationship::RelatedSpawnerCommands, prelude::*};
 
 use crate::{
-    assets::{ALIVE_ENEMIES_ICON, FONT, FONT_SIZE, GOLD_IMAGE_ICON, HEART_IMAGE},
-    enemy::Enemy,
+    assets::{
+        ALIVE_ENEMIES_ICON, BOSS_BAR_BACKGROUND_COLOR, BOSS_BAR_COLOR, BOSS_SHIELD_COLOR, FONT,
+        FONT_SIZE, GOLD_IMAGE_ICON, HEART_IMAGE,
+    },
+    boss::Boss,
+    economy::{EconomyRules, Ledger},
+    enemy::{Enemy, Shield},
+    path::regeneration::FreeRelocation,
     player::{Gold, Player},
-    stats::Health,
+    stats::{Damage, FireRate, Health, MaxHealth, Range},
+    tower::{SelectedTower, SelectedTowerKind, TargetFilter, Tower, TowerKind},
+    veterancy::{TowerRank, TowerRecord},
 };
 
 pub mod debug {
@@ -66,11 +74,32 @@ impl Plugin for UiOverlay {
         app.add_systems(Startup, prepare_ui_overlay);
         app.add_systems(
             Update,
-            (update_gold_label, update_health_label, update_enemies_count).in_set(UiSet),
+            (
+                update_gold_label,
+                update_ledger_label,
+                update_health_label,
+                update_enemies_count,
+                update_selected_tower_label,
+                update_tower_info_panel,
+                update_boss_health_bar,
+                expire_ui_messages,
+            )
+                .in_set(UiSet),
         );
+        app.add_observer(show_ui_message);
     }
 }
 
+/// Seconds a `UiMessage` stays on screen.
+static UI_MESSAGE_SECONDS: f32 = 5.0;
+
+/// Short notice shown to the player at the bottom of the screen.
+#[derive(Event, Debug, Clone)]
+pub struct UiMessage(pub String);
+
+#[derive(Component)]
+pub struct UiMessageText(pub Timer);
+
 #[derive(Resource, Deref)]
 pub struct UiFont(pub Handle<Font>);
 #[derive(Resource, Deref)]
@@ -85,6 +114,20 @@ pub struct HealthTextLabel;
 pub struct GoldTextLabel;
 #[derive(Component)]
 pub struct AliveEnemiesLabel;
+#[derive(Component)]
+pub struct LedgerLabel;
+#[derive(Component)]
+pub struct SelectedTowerLabel;
+#[derive(Component)]
+pub struct TowerInfoPanel;
+#[derive(Component)]
+pub struct TowerInfoText;
+#[derive(Component)]
+pub struct BossBar;
+#[derive(Component)]
+pub struct BossBarFill;
+#[derive(Component)]
+pub struct BossBarLabel;
 
 // TODO: only works for one player for now
 pub fn update_gold_label(
@@ -99,7 +142,7 @@ pub fn update_gold_label(
 }
 // TODO: only works for one player for now
 pub fn update_health_label(
-    gold_query: Query<(&mut Text), With<HealthTextLabel>>,
+    gold_query: Query<&mut Text, With<HealthTextLabel>>,
     player: Query<&Health, With<Player>>,
 ) {
     if let Ok(player_health) = player.single() {
@@ -111,13 +154,175 @@ pub fn update_health_label(
 
 pub fn update_enemies_count(
     text_query: Query<&mut Text, With<AliveEnemiesLabel>>,
-    enemies_query: Query<(Entity), With<Enemy>>,
+    enemies_query: Query<Entity, With<Enemy>>,
 ) {
     let count = enemies_query.iter().count();
     for mut t in text_query {
         t.0 = format!("{count}");
     }
 }
+pub fn update_selected_tower_label(
+    text_query: Query<&mut Text, With<SelectedTowerLabel>>,
+    selected: Res<SelectedTowerKind>,
+    economy: Res<EconomyRules>,
+) {
+    if selected.is_changed() || economy.is_changed() {
+        for mut t in text_query {
+            t.0 = format!(
+                "[1-5] tower: {} ({}g)",
+                selected.name(),
+                economy.tower_cost(**selected)
+            );
+        }
+    }
+}
+
+pub fn update_ledger_label(text_query: Query<&mut Text, With<LedgerLabel>>, ledger: Res<Ledger>) {
+    if !ledger.is_changed() {
+        return;
+    }
+    let mut lines = vec![format!("+{} / -{}", ledger.income, ledger.expenses)];
+    for entry in ledger.recent.iter().rev() {
+        lines.push(format!("{:+} {}", entry.amount, entry.reason.name()));
+    }
+    for mut t in text_query {
+        t.0 = lines.join("\n");
+    }
+}
+#[allow(clippy::type_complexity)]
+pub fn update_tower_info_panel(
+    mut panel: Query<&mut Node, With<TowerInfoPanel>>,
+    mut text: Query<&mut Text, With<TowerInfoText>>,
+    mut selected: ResMut<SelectedTower>,
+    towers: Query<
+        (
+            &TowerKind,
+            Option<&Damage>,
+            Option<&Range>,
+            Option<&FireRate>,
+            Option<&TowerRank>,
+            Option<&TowerRecord>,
+            Option<&TargetFilter>,
+            Has<FreeRelocation>,
+        ),
+        With<Tower>,
+    >,
+) {
+    let Ok(mut node) = panel.single_mut() else {
+        return;
+    };
+    let info = selected.and_then(|e| towers.get(e).ok());
+    let Some((kind, damage, range, fire_rate, rank, record, filter, relocatable)) = info else {
+        if selected.is_some() {
+            selected.0 = None;
+        }
+        node.display = Display::None;
+        return;
+    };
+    node.display = Display::Flex;
+    let mut lines = vec![format!("{} tower", kind.name())];
+    if let Some(filter) = filter {
+        lines.push(format!("targets: {}", filter.name()));
+    }
+    if let Some(damage) = damage {
+        lines.push(format!("damage: {:.1}", damage.0));
+    }
+    if let Some(range) = range {
+        lines.push(format!("range: {:.0}", range.0));
+    }
+    if let Some(fire_rate) = fire_rate {
+        lines.push(format!("fire rate: {:.0}/min", fire_rate.0));
+    }
+    if let Some(rank) = rank {
+        lines.push(format!("rank: {}", rank.0));
+    }
+    if let Some(record) = record {
+        match record.next_rank_xp() {
+            Some(next) => lines.push(format!("xp: {:.0}/{:.0}", record.xp, next)),
+            None => lines.push(format!("xp: {:.0}", record.xp)),
+        }
+        lines.push(format!("damage dealt: {:.0}", record.damage_dealt));
+        lines.push(format!("kills: {}", record.kills));
+    }
+    if relocatable {
+        lines.push("out of range: click an empty tile to move for free".to_string());
+    }
+    for mut t in &mut text {
+        t.0 = lines.join("\n");
+    }
+}
+
+pub fn show_ui_message(
+    trigger: Trigger<UiMessage>,
+    mut commands: Commands,
+    font: Res<UiFont>,
+    font_size: Res<UiFontSize>,
+    messages: Query<Entity, With<UiMessageText>>,
+) {
+    for old in messages {
+        commands.entity(old).despawn();
+    }
+    commands.spawn((
+        UiMessageText(Timer::from_seconds(UI_MESSAGE_SECONDS, TimerMode::Once)),
+        Text::new(trigger.event().0.clone()),
+        TextFont::default()
+            .with_font(font.0.clone())
+            .with_font_size(**font_size),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(60.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..Default::default()
+        },
+    ));
+}
+
+pub fn expire_ui_messages(
+    mut commands: Commands,
+    messages: Query<(Entity, &mut UiMessageText)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut message) in messages {
+        if message.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_boss_health_bar(
+    mut bar: Query<&mut Node, With<BossBar>>,
+    mut fill: Query<(&mut Node, &mut BackgroundColor), (With<BossBarFill>, Without<BossBar>)>,
+    mut label: Query<&mut Text, With<BossBarLabel>>,
+    bosses: Query<(&Name, &Health, &MaxHealth, Option<&Shield>), (With<Boss>, With<Enemy>)>,
+) {
+    let Ok(mut bar) = bar.single_mut() else {
+        return;
+    };
+    let Some((name, health, max_health, shield)) = bosses.iter().next() else {
+        bar.display = Display::None;
+        return;
+    };
+    bar.display = Display::Flex;
+    let fraction = (health.0 / max_health.0).clamp(0.0, 1.0);
+    for (mut node, mut color) in &mut fill {
+        node.width = Val::Percent(fraction * 100.0);
+        color.0 = if shield.is_some() {
+            BOSS_SHIELD_COLOR
+        } else {
+            BOSS_BAR_COLOR
+        };
+    }
+    for mut t in &mut label {
+        t.0 = match shield {
+            Some(s) => format!("{name} {:.0}/{:.0} (+{:.0})", health.0, max_health.0, s.0),
+            None => format!("{name} {:.0}/{:.0}", health.0, max_health.0),
+        };
+    }
+}
+
 pub fn prepare_ui_overlay(mut commands: Commands, assets: Res<AssetServer>) {
     let font = assets.load(FONT);
     let gold_image = assets.load(GOLD_IMAGE_ICON);
@@ -139,6 +344,96 @@ pub fn prepare_ui_overlay(mut commands: Commands, assets: Res<AssetServer>) {
             Pickable::IGNORE,
         ))
         .with_children(|builder| {
+            builder.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                Text::new(""),
+                TextFont::default()
+                    .with_font(font.clone())
+                    .with_font_size(FONT_SIZE),
+                SelectedTowerLabel,
+            ));
+            builder
+                .spawn((
+                    Node {
+                        display: Display::None,
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(35.0),
+                        left: Val::Px(10.0),
+                        padding: UiRect::all(Val::Px(8.0)),
+                        ..Default::default()
+                    },
+                    BackgroundColor(Color::hsla(0.0, 0.0, 0.0, 0.6)),
+                    TowerInfoPanel,
+                    Pickable::IGNORE,
+                ))
+                .with_child((
+                    Text::new(""),
+                    TextFont::default()
+                        .with_font(font.clone())
+                        .with_font_size(FONT_SIZE),
+                    TowerInfoText,
+                ));
+            builder.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(70.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                Text::new(""),
+                TextFont::default()
+                    .with_font(font.clone())
+                    .with_font_size(FONT_SIZE * 0.8),
+                TextLayout::new_with_justify(JustifyText::Right),
+                LedgerLabel,
+            ));
+            builder
+                .spawn((
+                    Node {
+                        display: Display::None,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(10.0),
+                        left: Val::Percent(30.0),
+                        width: Val::Percent(40.0),
+                        row_gap: Val::Px(4.0),
+                        ..Default::default()
+                    },
+                    BossBar,
+                    Pickable::IGNORE,
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Text::new(""),
+                        TextFont::default()
+                            .with_font(font.clone())
+                            .with_font_size(FONT_SIZE),
+                        BossBarLabel,
+                    ));
+                    bar.spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(14.0),
+                            ..Default::default()
+                        },
+                        BackgroundColor(BOSS_BAR_BACKGROUND_COLOR),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..Default::default()
+                        },
+                        BackgroundColor(BOSS_BAR_COLOR),
+                        BossBarFill,
+                    ));
+                });
             builder
                 .spawn((
                     Node {
@@ -0,0 +1,156 @@
+use bevy::{
+    app::{FixedUpdate, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        hierarchy::Children,
+        query::{Changed, With},
+        system::{Commands, Query, Res},
+    },
+    log::info,
+    prelude::{Deref, DerefMut},
+    text::{Text2d, TextColor, TextFont},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{FONT_SIZE, RANK_BADGE_COLOR},
+    stats::StatBonus,
+    tower::Tower,
+    ui::UiFont,
+};
+
+pub struct VeterancyPlugin;
+
+impl Plugin for VeterancyPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(FixedUpdate, promote_towers);
+    }
+}
+
+/// Shows the rank of every promoted tower as a badge.
+pub struct VeterancyUiPlugin;
+
+impl Plugin for VeterancyUiPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.add_systems(Update, update_rank_badges);
+    }
+}
+
+pub static KILL_XP: f32 = 25.0;
+/// Experience needed to reach rank `n + 1`.
+pub static RANK_THRESHOLDS: [f32; 5] = [60.0, 180.0, 400.0, 800.0, 1500.0];
+/// Bonus granted for every rank a tower has reached.
+pub static RANK_BONUS: StatBonus = StatBonus {
+    damage: 0.1,
+    range: 0.05,
+    fire_rate: 0.05,
+};
+
+/// Lifetime statistics of a shooting tower.
+#[derive(Component, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct TowerRecord {
+    pub xp: f32,
+    pub damage_dealt: f32,
+    pub kills: u32,
+}
+
+impl TowerRecord {
+    pub fn credit(&mut self, damage: f32, killed: bool) {
+        self.damage_dealt += damage;
+        self.xp += damage;
+        if killed {
+            self.kills += 1;
+            self.xp += KILL_XP;
+        }
+    }
+
+    pub fn rank(&self) -> u32 {
+        RANK_THRESHOLDS.iter().filter(|t| self.xp >= **t).count() as u32
+    }
+
+    /// Experience needed for the next rank, `None` at max rank.
+    pub fn next_rank_xp(&self) -> Option<f32> {
+        RANK_THRESHOLDS.get(self.rank() as usize).copied()
+    }
+}
+
+#[derive(
+    Component, Serialize, Deserialize, Default, Debug, Clone, Copy, Deref, DerefMut, PartialEq, Eq,
+)]
+pub struct TowerRank(pub u32);
+
+impl TowerRank {
+    pub fn bonus(&self) -> StatBonus {
+        RANK_BONUS.scaled(self.0 as f32)
+    }
+
+    pub fn badge(&self) -> String {
+        "*".repeat(self.0 as usize)
+    }
+}
+
+#[derive(Component)]
+pub struct RankBadge;
+
+pub fn promote_towers(query: Query<(&TowerRecord, &mut TowerRank), Changed<TowerRecord>>) {
+    for (record, mut rank) in query {
+        let new_rank = record.rank();
+        if rank.0 != new_rank {
+            info!("tower promoted to rank {new_rank}");
+            rank.0 = new_rank;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_rank_badges(
+    mut commands: Commands,
+    towers: Query<(Entity, &TowerRank, Option<&Children>), (With<Tower>, Changed<TowerRank>)>,
+    mut badges: Query<&mut Text2d, With<RankBadge>>,
+    font: Res<UiFont>,
+) {
+    for (tower, rank, children) in towers {
+        if rank.0 == 0 {
+            continue;
+        }
+        let existing = children
+            .into_iter()
+            .flatten()
+            .find(|c| badges.contains(**c))
+            .copied();
+        if let Some(badge) = existing
+            && let Ok(mut text) = badges.get_mut(badge)
+        {
+            text.0 = rank.badge();
+        } else {
+            commands.entity(tower).with_child((
+                RankBadge,
+                Text2d::new(rank.badge()),
+                TextFont::default()
+                    .with_font(font.0.clone())
+                    .with_font_size(FONT_SIZE),
+                TextColor(RANK_BADGE_COLOR),
+                Transform::from_xyz(0.0, -26.0, 1.0),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_follows_thresholds() {
+        let mut record = TowerRecord::default();
+        assert_eq!(record.rank(), 0);
+        record.credit(RANK_THRESHOLDS[0] - KILL_XP, true);
+        assert_eq!(record.rank(), 1);
+        assert_eq!(record.kills, 1);
+        record.credit(10_000.0, false);
+        assert_eq!(record.rank(), RANK_THRESHOLDS.len() as u32);
+        assert_eq!(record.next_rank_xp(), None);
+    }
+}
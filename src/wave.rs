@@ -0,0 +1,236 @@
+use bevy::{
+    app::Plugin,
+    asset::{Asset, AssetApp, Handle},
+    ecs::resource::Resource,
+    prelude::Deref,
+    reflect::TypePath,
+};
+use serde::Deserialize;
+
+use crate::assets::RonAssetLoader;
+
+pub struct WaveScriptPlugin;
+
+impl Plugin for WaveScriptPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_asset::<WaveScript>();
+        app.register_asset_loader(RonAssetLoader::<WaveScript>::new(&["waves.ron"]));
+    }
+}
+
+/// Multipliers applied on top of an archetype's wave scaled stats.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct EnemyModifiers {
+    pub health: f32,
+    pub speed: f32,
+    pub gold: f32,
+    /// Flat armor added to the archetype's.
+    pub armor: f32,
+}
+
+impl Default for EnemyModifiers {
+    fn default() -> Self {
+        Self {
+            health: 1.0,
+            speed: 1.0,
+            gold: 1.0,
+            armor: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpawnPoint {
+    /// Any path start, chosen at random for every enemy.
+    #[default]
+    Any,
+    /// The n-th path start, ordered by grid index.
+    Index(usize),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct WaveGroup {
+    /// Name of the `EnemyDefinition` to spawn.
+    pub enemy: String,
+    pub count: u32,
+    /// Seconds between two enemies of this group.
+    #[serde(default = "default_interval")]
+    pub interval: f32,
+    /// Seconds to wait after the previous group finished spawning.
+    #[serde(default)]
+    pub delay: f32,
+    #[serde(default)]
+    pub spawn: SpawnPoint,
+    #[serde(default)]
+    pub modifiers: EnemyModifiers,
+}
+
+fn default_interval() -> f32 {
+    1.0
+}
+
+impl WaveGroup {
+    /// `count` enemies of the archetype named `enemy`, one a second from the
+    /// start of the group, spawning anywhere without modifiers.
+    pub fn new(enemy: &str, count: u32) -> Self {
+        Self {
+            enemy: enemy.to_string(),
+            count,
+            interval: default_interval(),
+            delay: 0.0,
+            spawn: SpawnPoint::Any,
+            modifiers: EnemyModifiers::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct WaveDefinition {
+    pub groups: Vec<WaveGroup>,
+    /// Lays a second path for this wave that enemies also spawn on.
+    #[serde(default)]
+    pub second_path: bool,
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, Default)]
+pub struct WaveScript {
+    pub waves: Vec<WaveDefinition>,
+}
+
+impl WaveScript {
+    /// The definition for `wave`, repeating the last scripted wave once the script runs out.
+    pub fn get(&self, wave: u32) -> Option<&WaveDefinition> {
+        self.waves.get(wave as usize).or(self.waves.last())
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct WaveScriptHandle(pub Handle<WaveScript>);
+
+/// Runs the groups of a single wave one after another.
+#[derive(Resource, Debug, Clone)]
+pub struct WaveSchedule {
+    groups: Vec<WaveGroup>,
+    current: usize,
+    spawned_in_group: u32,
+    /// Seconds until the next enemy is due.
+    remaining: f32,
+}
+
+impl WaveSchedule {
+    pub fn new(wave: &WaveDefinition) -> Self {
+        let groups: Vec<WaveGroup> = wave
+            .groups
+            .iter()
+            .filter(|g| g.count > 0)
+            .cloned()
+            .collect();
+        let remaining = groups.first().map(|g| g.delay).unwrap_or_default();
+        Self {
+            groups,
+            current: 0,
+            spawned_in_group: 0,
+            remaining,
+        }
+    }
+
+    /// Advances the schedule and returns the groups that are due to spawn an
+    /// enemy, once per enemy.
+    pub fn tick(&mut self, delta_secs: f32) -> Vec<&WaveGroup> {
+        self.remaining -= delta_secs;
+        let mut due = vec![];
+        while self.remaining <= 0.0 && self.current < self.groups.len() {
+            due.push(self.current);
+            self.spawned_in_group += 1;
+            if self.spawned_in_group >= self.groups[self.current].count {
+                self.current += 1;
+                self.spawned_in_group = 0;
+                self.remaining += self.groups.get(self.current).map_or(0.0, |g| g.delay);
+            } else {
+                self.remaining += self.groups[self.current].interval;
+            }
+        }
+        due.into_iter().map(|i| &self.groups[i]).collect()
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.current >= self.groups.len()
+    }
+
+    pub fn remaining_enemies(&self) -> u32 {
+        self.groups
+            .iter()
+            .skip(self.current)
+            .map(|g| g.count)
+            .sum::<u32>()
+            - self.spawned_in_group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_respects_delays_and_intervals() {
+        let wave = WaveDefinition {
+            groups: vec![
+                WaveGroup {
+                    delay: 0.5,
+                    ..WaveGroup::new("a", 2)
+                },
+                WaveGroup {
+                    delay: 2.0,
+                    ..WaveGroup::new("b", 1)
+                },
+            ],
+            ..Default::default()
+        };
+        let mut schedule = WaveSchedule::new(&wave);
+        assert!(schedule.tick(0.25).is_empty());
+        let due: Vec<&str> = schedule
+            .tick(0.25)
+            .iter()
+            .map(|g| g.enemy.as_str())
+            .collect();
+        assert_eq!(due, vec!["a"]);
+        assert!(schedule.tick(0.75).is_empty());
+        assert_eq!(schedule.tick(0.25).len(), 1);
+        assert_eq!(schedule.remaining_enemies(), 1);
+        assert!(!schedule.is_exhausted());
+        assert!(schedule.tick(1.75).is_empty());
+        let due: Vec<&str> = schedule
+            .tick(0.25)
+            .iter()
+            .map(|g| g.enemy.as_str())
+            .collect();
+        assert_eq!(due, vec!["b"]);
+        assert!(schedule.is_exhausted());
+    }
+
+    #[test]
+    fn large_steps_spawn_several() {
+        let wave = WaveDefinition {
+            groups: vec![WaveGroup {
+                interval: 0.5,
+                ..WaveGroup::new("a", 3)
+            }],
+            ..Default::default()
+        };
+        let mut schedule = WaveSchedule::new(&wave);
+        assert_eq!(schedule.tick(5.0).len(), 3);
+        assert!(schedule.is_exhausted());
+    }
+
+    #[test]
+    fn shipped_script_parses() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/waves/default.waves.ron"
+        );
+        let text = std::fs::read_to_string(path).unwrap();
+        let script: WaveScript = ron::de::from_str(&text).unwrap();
+        assert!(!script.waves.is_empty());
+    }
+}